@@ -9,13 +9,35 @@ use nom::{
     character::complete::i32,
     combinator::value,
     combinator::{map_parser, opt},
+    error::{Error, ErrorKind},
     IResult,
 };
 
+/// How the first and last digit of each line are located.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScanMode {
+    /// Collect every overlapping digit in the line, then take the ends.
+    Collect,
+    /// Search forwards for the first digit and backwards for the last.
+    Bidirectional,
+}
+
 pub fn solution() -> Result<Solution<i32>> {
+    solution_with_mode(ScanMode::Bidirectional)
+}
+
+pub fn solution_with_mode(mode: ScanMode) -> Result<Solution<i32>> {
     let day = 1;
-    let part_1 = sum_of_calibration_values(day, part_1_parser)?;
-    let part_2 = sum_of_calibration_values(day, part_2_parser)?;
+    let (part_1, part_2) = match mode {
+        ScanMode::Collect => (
+            sum_of_calibration_values(day, collected_calibration_value(part_1_parser))?,
+            sum_of_calibration_values(day, collected_calibration_value(part_2_parser))?,
+        ),
+        ScanMode::Bidirectional => (
+            sum_of_calibration_values(day, scanned_calibration_value(single_digit))?,
+            sum_of_calibration_values(day, scanned_calibration_value(spelled_or_literal_digit))?,
+        ),
+    };
     Ok(Solution {
         day,
         part_1,
@@ -23,18 +45,40 @@ pub fn solution() -> Result<Solution<i32>> {
     })
 }
 
-fn sum_of_calibration_values(day: u8, line_parser: impl Fn(&str) -> Vec<i32>) -> Result<i32> {
+fn sum_of_calibration_values(
+    day: u8,
+    calibration_value: impl Fn(&str) -> Option<i32>,
+) -> Result<i32> {
     input_lines(day)?
-        .filter_map_ok(|line| {
-            let digits: Vec<i32> = line_parser(&line);
-            let first = digits.first()?;
-            let last = digits.last().unwrap_or(first);
-            Some(10 * first + last)
-        })
+        .filter_map_ok(|line| calibration_value(&line))
         .fold_ok(0, Add::add)
         .map_err(Into::into)
 }
 
+// Collects every overlapping digit in the line and keeps the first and last.
+fn collected_calibration_value(
+    line_parser: impl Fn(&str) -> Vec<i32>,
+) -> impl Fn(&str) -> Option<i32> {
+    move |line| {
+        let digits: Vec<i32> = line_parser(line);
+        let first = digits.first()?;
+        let last = digits.last().unwrap_or(first);
+        Some(10 * first + last)
+    }
+}
+
+// Scans forwards for the first digit and backwards for the last, stopping at
+// the first match in each direction.
+fn scanned_calibration_value(digit: DigitParser) -> impl Fn(&str) -> Option<i32> {
+    move |line| {
+        let (_, first) = find_first(digit)(line).ok()?;
+        let (_, last) = find_last(digit)(line).ok()?;
+        Some(10 * first + last)
+    }
+}
+
+type DigitParser = fn(&str) -> IResult<&str, i32>;
+
 fn part_1_parser(input: &str) -> Vec<i32> {
     find_all_overlapping(single_digit)(input).unwrap().1
 }
@@ -86,6 +130,29 @@ fn find_all_overlapping<'a, T>(
     }
 }
 
+fn find_first<'a, T>(
+    parser: impl Fn(&'a str) -> IResult<&str, T>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, T> {
+    move |input| {
+        input
+            .char_indices()
+            .find_map(|(index, _)| parser(&input[index..]).ok())
+            .ok_or_else(|| nom::Err::Error(Error::new(input, ErrorKind::Eof)))
+    }
+}
+
+fn find_last<'a, T>(
+    parser: impl Fn(&'a str) -> IResult<&str, T>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, T> {
+    move |input| {
+        input
+            .char_indices()
+            .rev()
+            .find_map(|(index, _)| parser(&input[index..]).ok())
+            .ok_or_else(|| nom::Err::Error(Error::new(input, ErrorKind::Eof)))
+    }
+}
+
 pub fn single<'a, T>(
     parser: impl Fn(&'a str) -> IResult<&str, T>,
 ) -> impl FnMut(&'a str) -> IResult<&'a str, T> {
@@ -132,6 +199,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_find_first_and_last() -> Result<()> {
+        assert_eq!(find_first(spelled_or_literal_digit)("xtwone3four")?.1, 2);
+        assert_eq!(find_last(spelled_or_literal_digit)("xtwone3four")?.1, 4);
+        assert_eq!(find_first(single_digit)("treb7uchet")?.1, 7);
+        assert_eq!(find_last(single_digit)("treb7uchet")?.1, 7);
+        assert!(find_first(single_digit)("abc").is_err());
+        assert!(find_last(single_digit)("").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scanned_matches_collected() {
+        let inputs = [
+            "two1nine",
+            "eightwothree",
+            "abcone2threexyz",
+            "xtwone3four",
+            "4nineeightseven2",
+            "zoneight234",
+            "7pqrstsixteen",
+            "seven3oneightp",
+            "oneight",
+            "nodigits",
+        ];
+        let collected = collected_calibration_value(part_2_parser);
+        let scanned = scanned_calibration_value(spelled_or_literal_digit);
+        for input in inputs {
+            assert_eq!(scanned(input), collected(input), "{input}");
+        }
+    }
+
     #[test]
     fn test_solution() -> Result<()> {
         assert_eq!(
@@ -145,3 +244,64 @@ mod tests {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::Bencher;
+
+    // Long lines with many digits, so collecting every match does much more
+    // work than stopping at the first from each end.
+    fn synthetic_lines() -> Vec<String> {
+        let words = [
+            "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+        ];
+        let mut seed: u64 = 2023;
+        (0..1_000)
+            .map(|_| {
+                (0..200)
+                    .map(|_| {
+                        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                        match (seed >> 33) % 16 {
+                            0 => words[(seed >> 40) as usize % words.len()].to_string(),
+                            1 => ((seed >> 40) % 10).to_string(),
+                            _ => char::from(b'a' + ((seed >> 40) % 26) as u8).to_string(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn bench_calibration(bencher: &mut Bencher, calibration_value: impl Fn(&str) -> Option<i32>) {
+        let lines = synthetic_lines();
+        bencher.iter(|| {
+            lines
+                .iter()
+                .filter_map(|line| calibration_value(line))
+                .sum::<i32>()
+        });
+    }
+
+    #[bench]
+    fn bench_collected_part_1(bencher: &mut Bencher) {
+        bench_calibration(bencher, collected_calibration_value(part_1_parser));
+    }
+
+    #[bench]
+    fn bench_scanned_part_1(bencher: &mut Bencher) {
+        bench_calibration(bencher, scanned_calibration_value(single_digit));
+    }
+
+    #[bench]
+    fn bench_collected_part_2(bencher: &mut Bencher) {
+        bench_calibration(bencher, collected_calibration_value(part_2_parser));
+    }
+
+    #[bench]
+    fn bench_scanned_part_2(bencher: &mut Bencher) {
+        bench_calibration(bencher, scanned_calibration_value(spelled_or_literal_digit));
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::HashSet;
+
+use crate::{Result, Solution};
+
+pub fn solution() -> Result<Solution<usize>> {
+    let input = include_str!("../input/10.txt");
+
+    let maze = Maze::try_from(input)?;
+    let main_loop = maze.main_loop()?;
+
+    Ok(Solution {
+        day: 10,
+        part_1: main_loop.len() / 2,
+        part_2: enclosed_by_area(&main_loop),
+    })
+}
+
+/// The main loop drawn with box-drawing characters, with `I` on every tile it
+/// encloses.
+pub fn explain() -> Result<String> {
+    let input = include_str!("../input/10.txt");
+    let maze = Maze::try_from(input)?;
+    Ok(maze.render(&maze.main_loop()?))
+}
+
+type Position = (usize, usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
+    }
+
+    fn step(self, (row, col): Position) -> Option<Position> {
+        match self {
+            Direction::North => Some((row.checked_sub(1)?, col)),
+            Direction::East => Some((row, col + 1)),
+            Direction::South => Some((row + 1, col)),
+            Direction::West => Some((row, col.checked_sub(1)?)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Pipe {
+    Vertical,
+    Horizontal,
+    NorthEast,
+    NorthWest,
+    SouthWest,
+    SouthEast,
+}
+
+impl Pipe {
+    const ALL: [Pipe; 6] = [
+        Pipe::Vertical,
+        Pipe::Horizontal,
+        Pipe::NorthEast,
+        Pipe::NorthWest,
+        Pipe::SouthWest,
+        Pipe::SouthEast,
+    ];
+
+    fn connections(self) -> [Direction; 2] {
+        match self {
+            Pipe::Vertical => [Direction::North, Direction::South],
+            Pipe::Horizontal => [Direction::East, Direction::West],
+            Pipe::NorthEast => [Direction::North, Direction::East],
+            Pipe::NorthWest => [Direction::North, Direction::West],
+            Pipe::SouthWest => [Direction::South, Direction::West],
+            Pipe::SouthEast => [Direction::South, Direction::East],
+        }
+    }
+
+    fn connects(self, direction: Direction) -> bool {
+        self.connections().contains(&direction)
+    }
+
+    fn from_char(tile: char) -> Option<Self> {
+        match tile {
+            '|' => Some(Pipe::Vertical),
+            '-' => Some(Pipe::Horizontal),
+            'L' => Some(Pipe::NorthEast),
+            'J' => Some(Pipe::NorthWest),
+            '7' => Some(Pipe::SouthWest),
+            'F' => Some(Pipe::SouthEast),
+            _ => None,
+        }
+    }
+
+    fn box_char(self) -> char {
+        match self {
+            Pipe::Vertical => '│',
+            Pipe::Horizontal => '─',
+            Pipe::NorthEast => '└',
+            Pipe::NorthWest => '┘',
+            Pipe::SouthWest => '┐',
+            Pipe::SouthEast => '┌',
+        }
+    }
+}
+
+/// The pipe grid, with the tile under `S` filled in with a pipe that joins
+/// up with two of its neighbours, preferring one that closes a loop.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Maze {
+    tiles: Vec<Vec<Option<Pipe>>>,
+    start: Position,
+}
+
+impl Maze {
+    fn get(&self, (row, col): Position) -> Option<Pipe> {
+        *self.tiles.get(row)?.get(col)?
+    }
+
+    /// The tiles of the loop through `S` in order, starting at `S`.
+    fn main_loop(&self) -> Result<Vec<Position>> {
+        let mut main_loop = vec![self.start];
+        let mut position = self.start;
+        let mut direction = self.get(self.start).ok_or("S is not a pipe")?.connections()[0];
+        loop {
+            let next = direction.step(position);
+            let pipe = next
+                .and_then(|next| self.get(next))
+                .filter(|pipe| pipe.connects(direction.opposite()))
+                .ok_or_else(|| {
+                    format!(
+                        "the loop breaks heading {direction:?} from row {}, column {}",
+                        position.0 + 1,
+                        position.1 + 1
+                    )
+                })?;
+            position = next.unwrap();
+            if position == self.start {
+                return Ok(main_loop);
+            }
+            main_loop.push(position);
+            let came_from = direction.opposite();
+            direction = pipe
+                .connections()
+                .into_iter()
+                .find(|connection| *connection != came_from)
+                .unwrap();
+        }
+    }
+
+    /// The tiles not on the loop with an odd number of north-facing loop
+    /// tiles to their left. Counting only the pipes that reach north means
+    /// `L-7` is one crossing but `L-J` is none.
+    fn inside(&self, main_loop: &[Position]) -> HashSet<Position> {
+        let on_loop = main_loop.iter().copied().collect::<HashSet<_>>();
+        let mut inside = HashSet::new();
+        for (row, tiles) in self.tiles.iter().enumerate() {
+            let mut crossings = 0;
+            for (col, tile) in tiles.iter().enumerate() {
+                if on_loop.contains(&(row, col)) {
+                    if tile.is_some_and(|pipe| pipe.connects(Direction::North)) {
+                        crossings += 1;
+                    }
+                } else if crossings % 2 == 1 {
+                    inside.insert((row, col));
+                }
+            }
+        }
+        inside
+    }
+
+    fn render(&self, main_loop: &[Position]) -> String {
+        let on_loop = main_loop.iter().copied().collect::<HashSet<_>>();
+        let inside = self.inside(main_loop);
+        let mut output = String::new();
+        for (row, tiles) in self.tiles.iter().enumerate() {
+            for (col, tile) in tiles.iter().enumerate() {
+                output.push(match tile {
+                    Some(pipe) if on_loop.contains(&(row, col)) => pipe.box_char(),
+                    _ if inside.contains(&(row, col)) => 'I',
+                    _ => '.',
+                });
+            }
+            output.push('\n');
+        }
+        output
+    }
+}
+
+impl TryFrom<&str> for Maze {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(input: &str) -> Result<Self> {
+        let mut start = None;
+        let mut tiles = vec![];
+        for (row, line) in input.lines().enumerate() {
+            let mut row_tiles = vec![];
+            for (col, tile) in line.chars().enumerate() {
+                match tile {
+                    'S' if start.is_some() => return Err("more than one S".into()),
+                    'S' => start = Some((row, col)),
+                    '.' => {}
+                    _ if Pipe::from_char(tile).is_none() => {
+                        return Err(format!(
+                            "unexpected tile {tile:?} at row {}, column {}",
+                            row + 1,
+                            col + 1
+                        )
+                        .into())
+                    }
+                    _ => {}
+                }
+                row_tiles.push(Pipe::from_char(tile));
+            }
+            tiles.push(row_tiles);
+        }
+        let start = start.ok_or("no S")?;
+
+        let mut maze = Maze { tiles, start };
+        let connections = Direction::ALL
+            .into_iter()
+            .filter(|direction| {
+                direction
+                    .step(start)
+                    .and_then(|neighbour| maze.get(neighbour))
+                    .is_some_and(|pipe| pipe.connects(direction.opposite()))
+            })
+            .collect::<Vec<_>>();
+        let candidates = Pipe::ALL
+            .into_iter()
+            .filter(|pipe| {
+                pipe.connections()
+                    .iter()
+                    .all(|direction| connections.contains(direction))
+            })
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return Err(
+                format!("S joins {} pipes instead of at least 2", connections.len()).into(),
+            );
+        }
+        // With more than two neighbours joining S, only some pipes close a
+        // loop. If none does, keep the first so `main_loop` says where the
+        // loop breaks.
+        let start_pipe = candidates
+            .iter()
+            .copied()
+            .find(|pipe| {
+                maze.tiles[start.0][start.1] = Some(*pipe);
+                maze.main_loop().is_ok()
+            })
+            .unwrap_or(candidates[0]);
+        maze.tiles[start.0][start.1] = Some(start_pipe);
+        Ok(maze)
+    }
+}
+
+/// Counts the enclosed tiles from the area of the loop polygon, found with
+/// the shoelace formula, and Pick's theorem: the area is the number of
+/// enclosed tiles plus half the loop length, less one.
+fn enclosed_by_area(main_loop: &[Position]) -> usize {
+    let twice_area = main_loop
+        .iter()
+        .zip(main_loop.iter().cycle().skip(1))
+        .map(|(&(row, col), &(next_row, next_col))| {
+            col as i64 * next_row as i64 - next_col as i64 * row as i64
+        })
+        .sum::<i64>()
+        .unsigned_abs() as usize;
+    (twice_area + 2 - main_loop.len()) / 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = ".....
+.S-7.
+.|.|.
+.L-J.
+.....";
+
+    const TANGLED: &str = "-L|F7
+7S-7|
+L|7||
+-L-J|
+L|-JF";
+
+    const WINDING: &str = "7-F7-
+.FJ|7
+SJLL7
+|F--J
+LJ.LJ";
+
+    const ENCLOSED: [(&str, usize); 3] = [
+        (
+            "...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........",
+            4,
+        ),
+        (
+            "..........
+.S------7.
+.|F----7|.
+.||....||.
+.||....||.
+.|L-7F-J|.
+.|..||..|.
+.L--JL--J.
+..........",
+            4,
+        ),
+        (
+            ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...",
+            8,
+        ),
+    ];
+
+    #[test]
+    fn test_farthest() -> Result<()> {
+        for (input, farthest) in [(SQUARE, 4), (TANGLED, 4), (WINDING, 8)] {
+            assert_eq!(Maze::try_from(input)?.main_loop()?.len() / 2, farthest);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_enclosed() -> Result<()> {
+        for (input, enclosed) in ENCLOSED {
+            let maze = Maze::try_from(input)?;
+            let main_loop = maze.main_loop()?;
+            assert_eq!(enclosed_by_area(&main_loop), enclosed);
+            assert_eq!(maze.inside(&main_loop).len(), enclosed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_start_pipe() -> Result<()> {
+        assert_eq!(Maze::try_from(SQUARE)?.get((1, 1)), Some(Pipe::SouthEast));
+        assert_eq!(Maze::try_from(TANGLED)?.get((1, 1)), Some(Pipe::SouthEast));
+        assert_eq!(Maze::try_from(WINDING)?.get((2, 0)), Some(Pipe::SouthEast));
+
+        // The pipe to the west joins S too, but only `F` closes the loop.
+        let maze = Maze::try_from(".....\n-S-7.\n.|.|.\n.L-J.\n.....")?;
+        assert_eq!(maze.get((1, 1)), Some(Pipe::SouthEast));
+        assert_eq!(maze.main_loop()?.len(), 8);
+
+        let err = Maze::try_from(".S.\n...").err().unwrap();
+        assert_eq!(err.to_string(), "S joins 0 pipes instead of at least 2");
+        let err = Maze::try_from(".|.\n.S.\n...").err().unwrap();
+        assert_eq!(err.to_string(), "S joins 1 pipes instead of at least 2");
+        let maze = Maze::try_from(".|.\n-S-\n.|.")?;
+        assert_eq!(
+            maze.main_loop().err().unwrap().to_string(),
+            "the loop breaks heading North from row 1, column 2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_main_loop() -> Result<()> {
+        assert_eq!(
+            Maze::try_from(SQUARE)?.main_loop()?,
+            vec![
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (3, 2),
+                (3, 3),
+                (2, 3),
+                (1, 3),
+                (1, 2)
+            ]
+        );
+
+        let maze = Maze::try_from("S-7\n|.|\nL-.")?;
+        assert_eq!(
+            maze.main_loop().err().unwrap().to_string(),
+            "the loop breaks heading East from row 3, column 2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_render() -> Result<()> {
+        let maze = Maze::try_from(SQUARE)?;
+        assert_eq!(
+            maze.render(&maze.main_loop()?),
+            "\
+.....
+.┌─┐.
+.│I│.
+.└─┘.
+.....
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unexpected_tile() {
+        let err = Maze::try_from("S-7\n|X|\nL-J").err().unwrap();
+        assert_eq!(err.to_string(), "unexpected tile 'X' at row 2, column 2");
+    }
+}
@@ -0,0 +1,191 @@
+use crate::{Result, Solution};
+
+pub fn solution() -> Result<Solution<u64>> {
+    solution_with_expansion(1_000_000)
+}
+
+/// Part 1 doubles every empty row and column; part 2 makes each of them
+/// `expansion` rows or columns wide, which must be at least 1.
+pub fn solution_with_expansion(expansion: u64) -> Result<Solution<u64>> {
+    let input = include_str!("../input/11.txt");
+
+    if expansion == 0 {
+        return Err("expansion factor must be at least 1".into());
+    }
+    let image = Image::try_from(input)?;
+
+    let part_1 = image
+        .sum_of_distances(2)
+        .ok_or("sum of distances overflowed")?;
+    let part_2 = image
+        .sum_of_distances(expansion)
+        .ok_or("sum of distances overflowed")?;
+
+    Ok(Solution {
+        day: 11,
+        part_1,
+        part_2,
+    })
+}
+
+/// The galaxies in the image as (row, column), in reading order.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Image {
+    galaxies: Vec<(usize, usize)>,
+    height: usize,
+    width: usize,
+}
+
+impl Image {
+    /// The sum of the Manhattan distances between every pair of galaxies once
+    /// each empty row and column is `expansion` times as wide, or `None` on
+    /// overflow. An `expansion` of 0 is read as 1, though
+    /// `solution_with_expansion` rejects it outright.
+    pub fn sum_of_distances(&self, expansion: u64) -> Option<u64> {
+        let rows = self
+            .galaxies
+            .iter()
+            .map(|(row, _)| *row)
+            .collect::<Vec<_>>();
+        let cols = self
+            .galaxies
+            .iter()
+            .map(|(_, col)| *col)
+            .collect::<Vec<_>>();
+        sum_of_gaps(expand(&rows, self.height, expansion)?)?
+            .checked_add(sum_of_gaps(expand(&cols, self.width, expansion)?)?)
+    }
+}
+
+impl TryFrom<&str> for Image {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(input: &str) -> Result<Self> {
+        let mut galaxies = vec![];
+        let mut width = 0;
+        for (row, line) in input.lines().enumerate() {
+            for (col, pixel) in line.chars().enumerate() {
+                match pixel {
+                    '#' => galaxies.push((row, col)),
+                    '.' => {}
+                    _ => {
+                        return Err(format!(
+                            "unexpected pixel {pixel:?} at row {}, column {}",
+                            row + 1,
+                            col + 1
+                        )
+                        .into())
+                    }
+                }
+            }
+            width = width.max(line.chars().count());
+        }
+        Ok(Self {
+            galaxies,
+            height: input.lines().count(),
+            width,
+        })
+    }
+}
+
+/// Where each of `coordinates` along an axis of length `size` ends up once
+/// every empty line along it is `expansion` lines wide. A running count of
+/// the empty lines so far gives each shift without building the expanded
+/// image.
+fn expand(coordinates: &[usize], size: usize, expansion: u64) -> Option<Vec<u64>> {
+    let mut occupied = vec![false; size];
+    for coordinate in coordinates {
+        occupied[*coordinate] = true;
+    }
+    let empty_before = occupied
+        .iter()
+        .scan(0_u64, |empty, occupied| {
+            let before = *empty;
+            if !occupied {
+                *empty += 1;
+            }
+            Some(before)
+        })
+        .collect::<Vec<_>>();
+    coordinates
+        .iter()
+        .map(|coordinate| {
+            empty_before[*coordinate]
+                .checked_mul(expansion.saturating_sub(1))?
+                .checked_add(*coordinate as u64)
+        })
+        .collect()
+}
+
+/// The sum of `|a - b|` over every pair of `coordinates`. Once sorted, each
+/// coordinate is at least every one before it, so it contributes itself
+/// times its index less the sum of those before it.
+fn sum_of_gaps(mut coordinates: Vec<u64>) -> Option<u64> {
+    coordinates.sort_unstable();
+    let mut before = 0_u64;
+    let mut sum = 0_u64;
+    for (index, coordinate) in (0_u64..).zip(coordinates) {
+        sum = sum.checked_add(coordinate.checked_mul(index)? - before)?;
+        before = before.checked_add(coordinate)?;
+    }
+    Some(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+
+    #[test]
+    fn test_sum_of_distances() -> Result<()> {
+        let image = Image::try_from(EXAMPLE)?;
+        assert_eq!(image.sum_of_distances(0), Some(292));
+        assert_eq!(image.sum_of_distances(1), Some(292));
+        assert_eq!(image.sum_of_distances(2), Some(374));
+        assert_eq!(image.sum_of_distances(10), Some(1030));
+        assert_eq!(image.sum_of_distances(100), Some(8410));
+        assert_eq!(image.sum_of_distances(u64::MAX), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(expand(&[0, 2, 5], 6, 2), Some(vec![0, 3, 8]));
+        assert_eq!(expand(&[0, 2, 5], 6, 1), Some(vec![0, 2, 5]));
+        assert_eq!(expand(&[1], 2, 10), Some(vec![10]));
+        assert_eq!(expand(&[2], 3, u64::MAX), None);
+    }
+
+    #[test]
+    fn test_sum_of_gaps() {
+        assert_eq!(sum_of_gaps(vec![]), Some(0));
+        assert_eq!(sum_of_gaps(vec![4, 1, 7]), Some(3 + 6 + 3));
+        assert_eq!(sum_of_gaps(vec![0, u64::MAX, u64::MAX]), None);
+    }
+
+    #[test]
+    fn test_image() -> Result<()> {
+        let image = Image::try_from("#.\n.#\n..")?;
+        assert_eq!(
+            image,
+            Image {
+                galaxies: vec![(0, 0), (1, 1)],
+                height: 3,
+                width: 2
+            }
+        );
+        let err = Image::try_from("#.\n.@").err().unwrap();
+        assert_eq!(err.to_string(), "unexpected pixel '@' at row 2, column 2");
+        Ok(())
+    }
+}
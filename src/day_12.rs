@@ -0,0 +1,262 @@
+use nom::{
+    character::complete::{char, one_of, space1, u32},
+    combinator::{all_consuming, map, verify},
+    multi::{many1, separated_list1},
+    sequence::separated_pair,
+    Finish, IResult,
+};
+use rayon::prelude::*;
+
+use crate::{Result, Solution};
+
+pub fn solution() -> Result<Solution<u64>> {
+    let input = include_str!("../input/12.txt");
+
+    let records = parse_records(input)?;
+
+    let part_1 = total_arrangements(&records, 1).ok_or("arrangement count overflowed")?;
+    let part_2 = total_arrangements(&records, 5).ok_or("arrangement count overflowed")?;
+
+    Ok(Solution {
+        day: 12,
+        part_1,
+        part_2,
+    })
+}
+
+/// The arrangements of every record unfolded `copies` times, counted in
+/// parallel across records. `None` on overflow.
+fn total_arrangements(records: &[Record], copies: usize) -> Option<u64> {
+    records
+        .par_iter()
+        .map(|record| record.unfold(copies).arrangements())
+        .try_reduce(|| 0, u64::checked_add)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Spring {
+    Operational,
+    Damaged,
+    Unknown,
+}
+
+/// A row of springs and the sizes of its contiguous groups of damaged
+/// springs, in order.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Record {
+    springs: Vec<Spring>,
+    groups: Vec<usize>,
+}
+
+impl Record {
+    /// `copies` copies of the springs joined by unknown springs, with the
+    /// groups repeated to match.
+    fn unfold(&self, copies: usize) -> Record {
+        let mut springs = self.springs.clone();
+        for _ in 1..copies {
+            springs.push(Spring::Unknown);
+            springs.extend(&self.springs);
+        }
+        Record {
+            springs,
+            groups: self.groups.repeat(copies),
+        }
+    }
+
+    /// The number of ways to fill in the unknown springs that match the
+    /// groups, or `None` on overflow.
+    ///
+    /// `ways[i][g]` counts the ways to arrange `springs[i..]` into
+    /// `groups[g..]`, filled in from the end. A spring that may be
+    /// operational leaves `g` as it is for `i + 1`; one that may be damaged
+    /// can start group `g` if enough springs follow that may be damaged and
+    /// the one after them may be operational. A count that overflows is
+    /// stored as `None`, so only overflow that reaches `ways[0][0]` counts.
+    fn arrangements(&self) -> Option<u64> {
+        let (springs, groups) = (&self.springs, &self.groups);
+        let n = springs.len();
+
+        // The number of springs from each position on that may be damaged.
+        let mut damaged_run = vec![0; n + 1];
+        for i in (0..n).rev() {
+            if springs[i] != Spring::Operational {
+                damaged_run[i] = damaged_run[i + 1] + 1;
+            }
+        }
+
+        let mut ways = vec![vec![Some(0_u64); groups.len() + 1]; n + 1];
+        ways[n][groups.len()] = Some(1);
+        for i in (0..n).rev() {
+            for g in 0..=groups.len() {
+                let mut count = Some(0_u64);
+                if springs[i] != Spring::Damaged {
+                    count = ways[i + 1][g];
+                }
+                if springs[i] != Spring::Operational && g < groups.len() {
+                    let end = i + groups[g];
+                    if damaged_run[i] >= groups[g] && (end == n || springs[end] != Spring::Damaged)
+                    {
+                        let after = ways[(end + 1).min(n)][g + 1];
+                        count = count.and_then(|count| count.checked_add(after?));
+                    }
+                }
+                ways[i][g] = count;
+            }
+        }
+        ways[0][0]
+    }
+}
+
+pub fn parse_records(input: &str) -> Result<Vec<Record>> {
+    input
+        .lines()
+        .map(|line| {
+            let (_, record) = all_consuming(record)(line)
+                .finish()
+                .map_err(|err| format!("invalid record {line:?}: {err}"))?;
+            Ok(record)
+        })
+        .collect()
+}
+
+fn record(input: &str) -> IResult<&str, Record> {
+    map(
+        separated_pair(
+            many1(map(one_of(".#?"), |spring| match spring {
+                '.' => Spring::Operational,
+                '#' => Spring::Damaged,
+                _ => Spring::Unknown,
+            })),
+            space1,
+            separated_list1(
+                char(','),
+                map(verify(u32, |group| *group > 0), |group| group as usize),
+            ),
+        ),
+        |(springs, groups)| Record { springs, groups },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "???.### 1,1,3
+.??..??...?##. 1,1,3
+?#?#?#?#?#?#?#? 1,3,1,6
+????.#...#... 4,1,1
+????.######..#####. 1,6,5
+?###???????? 3,2,1";
+
+    #[test]
+    fn test_arrangements() -> Result<()> {
+        let records = parse_records(EXAMPLE)?;
+        let counts = |copies| {
+            records
+                .iter()
+                .map(|record| record.unfold(copies).arrangements())
+                .collect::<Option<Vec<_>>>()
+        };
+        assert_eq!(counts(1), Some(vec![1, 4, 1, 1, 4, 10]));
+        assert_eq!(counts(5), Some(vec![1, 16384, 1, 16, 2500, 506250]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_total_arrangements() -> Result<()> {
+        let records = parse_records(EXAMPLE)?;
+        assert_eq!(total_arrangements(&records, 1), Some(21));
+        assert_eq!(total_arrangements(&records, 5), Some(525152));
+        Ok(())
+    }
+
+    #[test]
+    fn test_arrangements_edge_cases() -> Result<()> {
+        let count = |line| -> Result<Option<u64>> { Ok(parse_records(line)?[0].arrangements()) };
+        assert_eq!(count("### 3")?, Some(1));
+        assert_eq!(count("### 2")?, Some(0));
+        assert_eq!(count("#.# 1")?, Some(0));
+        assert_eq!(count("??? 1,1")?, Some(1));
+        // Choosing 60 of 241 places overflows u64.
+        let line = format!("{} {}", "?".repeat(300), vec!["1"; 60].join(","));
+        assert_eq!(count(&line)?, None);
+        // The same groups overflow further in, but two leading damaged
+        // springs can't start any arrangement.
+        let line = format!("##{} {}", "?".repeat(300), vec!["1"; 61].join(","));
+        assert_eq!(count(&line)?, Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unfold() -> Result<()> {
+        let record = &parse_records(".# 1")?[0];
+        assert_eq!(record.unfold(3), parse_records(".#?.#?.# 1,1,1")?[0]);
+        assert_eq!(record.unfold(1), *record);
+        Ok(())
+    }
+
+    #[test]
+    fn test_record() {
+        assert!(record("???.### 1,1,3").is_ok());
+        assert!(parse_records("???.### 1,1,").is_err());
+        assert!(parse_records("??x.### 1,1,3").is_err());
+        assert!(parse_records("??? 0,1").is_err());
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use itertools::Itertools;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    // Tries every way of filling in the unknown springs.
+    fn brute_force(record: &Record) -> u64 {
+        let unknown = record
+            .springs
+            .iter()
+            .positions(|spring| *spring == Spring::Unknown)
+            .collect::<Vec<_>>();
+        (0..1_u32 << unknown.len())
+            .filter(|choice| {
+                let mut springs = record.springs.clone();
+                for (bit, position) in unknown.iter().enumerate() {
+                    springs[*position] = if choice >> bit & 1 == 1 {
+                        Spring::Damaged
+                    } else {
+                        Spring::Operational
+                    };
+                }
+                let groups = springs
+                    .split(|spring| *spring == Spring::Operational)
+                    .map(<[Spring]>::len)
+                    .filter(|len| *len > 0)
+                    .collect::<Vec<_>>();
+                groups == record.groups
+            })
+            .count() as u64
+    }
+
+    fn record() -> impl Strategy<Value = Record> {
+        (
+            prop::collection::vec(
+                prop_oneof![
+                    Just(Spring::Operational),
+                    Just(Spring::Damaged),
+                    Just(Spring::Unknown)
+                ],
+                1..12,
+            ),
+            prop::collection::vec(1_usize..4, 1..4),
+        )
+            .prop_map(|(springs, groups)| Record { springs, groups })
+    }
+
+    proptest! {
+        #[test]
+        fn arrangements_match_brute_force(record in record()) {
+            prop_assert_eq!(record.arrangements(), Some(brute_force(&record)));
+        }
+    }
+}
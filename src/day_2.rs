@@ -1,90 +1,230 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::str::FromStr;
+
 use crate::Result;
 use crate::Solution;
 use itertools::Itertools;
-use nom::branch::alt;
 use nom::bytes::complete::tag;
-use nom::character::complete::i32;
+use nom::character::complete::alpha1;
+use nom::character::complete::u32;
+use nom::combinator::all_consuming;
 use nom::combinator::map;
-use nom::combinator::value;
+use nom::combinator::map_opt;
 use nom::multi::separated_list1;
 use nom::sequence::tuple;
+use nom::Finish;
 use nom::IResult;
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-enum Cube {
-    Red,
-    Green,
-    Blue,
+/// Number of cubes of each colour, keyed by colour name. Colours that are
+/// absent count as zero.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Round {
+    counts: BTreeMap<String, u32>,
+}
+
+impl Round {
+    fn get(&self, colour: &str) -> u32 {
+        self.counts.get(colour).copied().unwrap_or(0)
+    }
+
+    /// Product of the counts of `colours`, or `None` on overflow.
+    fn power<'a>(&self, colours: impl IntoIterator<Item = &'a str>) -> Option<u64> {
+        colours.into_iter().try_fold(1_u64, |power, colour| {
+            power.checked_mul(self.get(colour).into())
+        })
+    }
+
+    fn size(&self) -> u64 {
+        self.counts.values().copied().map(u64::from).sum()
+    }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
-struct Round {
-    red: i32,
-    green: i32,
-    blue: i32,
+impl Display for Round {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts = self
+            .counts
+            .iter()
+            .map(|(colour, count)| format!("{count} {colour}"))
+            .join(", ");
+        write!(f, "{counts}")
+    }
 }
 
-impl Round {
-    fn power(&self) -> i32 {
-        self.red * self.green * self.blue
+impl<const N: usize> From<[(&str, u32); N]> for Round {
+    fn from(counts: [(&str, u32); N]) -> Self {
+        Self {
+            counts: counts
+                .into_iter()
+                .map(|(colour, count)| (colour.to_string(), count))
+                .collect(),
+        }
+    }
+}
+
+/// Parses a bag in the same format as a round, e.g. `12 red, 13 green, 14 blue`.
+impl FromStr for Round {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match round(input).finish() {
+            Ok(("", round)) => Ok(round),
+            Ok((remainder, _)) => Err(format!("unexpected trailing input: {remainder:?}")),
+            Err(err) => Err(err.to_string()),
+        }
     }
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 struct Game {
-    id: i32,
+    id: u32,
     rounds: Vec<Round>,
 }
 
-type CubeCount = Round;
+pub type CubeCount = Round;
 
 impl Game {
     fn is_possible(&self, cube_count: &CubeCount) -> bool {
         self.rounds.iter().all(|round| {
-            round.red <= cube_count.red
-                && round.green <= cube_count.green
-                && round.blue <= cube_count.blue
+            round
+                .counts
+                .iter()
+                .all(|(colour, count)| *count <= cube_count.get(colour))
         })
     }
 
     fn min_cube_count(&self) -> CubeCount {
-        self.rounds.iter().fold(
-            CubeCount {
-                red: i32::MIN,
-                green: i32::MIN,
-                blue: i32::MIN,
-            },
-            |min_count, round| CubeCount {
-                red: min_count.red.max(round.red),
-                green: min_count.green.max(round.green),
-                blue: min_count.blue.max(round.blue),
-            },
-        )
+        self.rounds
+            .iter()
+            .fold(CubeCount::default(), |mut min_count, round| {
+                for (colour, count) in &round.counts {
+                    let min = min_count.counts.entry(colour.clone()).or_insert(*count);
+                    *min = (*min).max(*count);
+                }
+                min_count
+            })
     }
 }
 
-pub fn solution() -> Result<Solution<i32>> {
-    let day = 2;
-    let cube_counts = CubeCount {
-        red: 12,
-        green: 13,
-        blue: 14,
-    };
-
-    let part_1 = crate::input_lines(day)?.fold_ok(0, |sum, line| {
-        let (_, game) = game(&line).unwrap();
-        if game.is_possible(&cube_counts) {
-            sum + game.id
-        } else {
-            sum
+/// The games parsed from an input, with queries beyond the two puzzle parts.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Games {
+    games: Vec<Game>,
+}
+
+impl Games {
+    pub fn load() -> Result<Self> {
+        crate::input_lines(2)?
+            .map(|line| parse_game(&line?))
+            .collect()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &Game> {
+        self.games.iter()
+    }
+
+    pub fn colours(&self) -> BTreeSet<&str> {
+        self.iter()
+            .flat_map(|game| &game.rounds)
+            .flat_map(|round| round.counts.keys())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The smallest number of `colour` cubes for which exactly `k` games are
+    /// possible, ignoring every other colour. `None` if no limit admits
+    /// exactly `k` games, e.g. because several games tie on the same draw.
+    pub fn min_limit_admitting(&self, colour: &str, k: usize) -> Option<u32> {
+        let draws = self
+            .iter()
+            .map(|game| game.min_cube_count().get(colour))
+            .sorted()
+            .collect_vec();
+        let limit = match k {
+            0 => 0,
+            k => *draws.get(k - 1)?,
+        };
+        match draws.get(k) {
+            Some(next) if *next <= limit => None,
+            _ => Some(limit),
         }
-    })?;
+    }
+
+    /// `min_limit_admitting` for every colour seen in the games.
+    pub fn min_limits_admitting(&self, k: usize) -> BTreeMap<&str, Option<u32>> {
+        self.colours()
+            .into_iter()
+            .map(|colour| (colour, self.min_limit_admitting(colour, k)))
+            .collect()
+    }
+
+    /// The largest draw of each colour in each game, keyed by game id.
+    pub fn max_draws(&self) -> BTreeMap<u32, CubeCount> {
+        self.iter()
+            .map(|game| (game.id, game.min_cube_count()))
+            .collect()
+    }
 
-    let part_2 = crate::input_lines(day)?.fold_ok(0, |sum, line| {
-        let (_, game) = game(&line).unwrap();
-        let min_cube_count = game.min_cube_count();
-        sum + min_cube_count.power()
-    })?;
+    /// How many rounds drew each total number of cubes.
+    pub fn round_size_distribution(&self) -> BTreeMap<u64, usize> {
+        self.iter()
+            .flat_map(|game| &game.rounds)
+            .map(Round::size)
+            .counts()
+            .into_iter()
+            .collect()
+    }
+
+    pub fn stats(&self, k: usize) -> String {
+        let mut stats = format!("Minimum bag admitting exactly {k} games:\n");
+        for (colour, limit) in self.min_limits_admitting(k) {
+            match limit {
+                Some(limit) => stats += &format!("  {colour}: {limit}\n"),
+                None => stats += &format!("  {colour}: none\n"),
+            }
+        }
+        stats += "Max draw per game:\n";
+        for (id, draws) in self.max_draws() {
+            stats += &format!("  Game {id}: {draws}\n");
+        }
+        stats += "Round sizes:\n";
+        for (size, count) in self.round_size_distribution() {
+            stats += &format!("  {size}: {count}\n");
+        }
+        stats
+    }
+}
+
+impl FromIterator<Game> for Games {
+    fn from_iter<I: IntoIterator<Item = Game>>(games: I) -> Self {
+        Self {
+            games: games.into_iter().collect(),
+        }
+    }
+}
+
+pub fn solution() -> Result<Solution<u64>> {
+    solution_with_bag(&CubeCount::from([("red", 12), ("green", 13), ("blue", 14)]))
+}
+
+pub fn solution_with_bag(cube_counts: &CubeCount) -> Result<Solution<u64>> {
+    let games = Games::load()?;
+
+    let part_1 = games
+        .iter()
+        .filter(|game| game.is_possible(cube_counts))
+        .try_fold(0_u64, |sum, game| sum.checked_add(game.id.into()))
+        .ok_or("sum of possible game ids overflowed")?;
+
+    // Colours a game never draws need zero cubes, making its power zero.
+    let colours = games.colours();
+    let part_2 = games
+        .iter()
+        .try_fold(0_u64, |sum, game| {
+            let power = game.min_cube_count().power(colours.iter().copied())?;
+            sum.checked_add(power)
+        })
+        .ok_or("sum of game powers overflowed")?;
 
     Ok(Solution {
         day: 2,
@@ -93,35 +233,23 @@ pub fn solution() -> Result<Solution<i32>> {
     })
 }
 
-fn cube(input: &str) -> IResult<&str, Cube> {
-    alt((
-        value(Cube::Red, tag("red")),
-        value(Cube::Green, tag("green")),
-        value(Cube::Blue, tag("blue")),
-    ))(input)
+fn cube(input: &str) -> IResult<&str, &str> {
+    alpha1(input)
 }
 
-fn cube_count(input: &str) -> IResult<&str, (Cube, i32)> {
-    map(tuple((i32, tag(" "), cube)), |pair| (pair.2, pair.0))(input)
+fn cube_count(input: &str) -> IResult<&str, (&str, u32)> {
+    map(tuple((u32, tag(" "), cube)), |pair| (pair.2, pair.0))(input)
 }
 
 fn round(input: &str) -> IResult<&str, Round> {
-    map(separated_list1(tag(", "), cube_count), |colour_counts| {
-        colour_counts.iter().fold(
-            Round {
-                red: 0,
-                green: 0,
-                blue: 0,
-            },
-            |mut round, (colour, count)| {
-                match colour {
-                    Cube::Red => round.red += count,
-                    Cube::Green => round.green += count,
-                    Cube::Blue => round.blue += count,
-                }
-                round
-            },
-        )
+    map_opt(separated_list1(tag(", "), cube_count), |colour_counts| {
+        colour_counts
+            .iter()
+            .try_fold(Round::default(), |mut round, (colour, count)| {
+                let total = round.counts.entry(colour.to_string()).or_default();
+                *total = total.checked_add(*count)?;
+                Some(round)
+            })
     })(input)
 }
 
@@ -129,29 +257,39 @@ fn rounds(input: &str) -> IResult<&str, Vec<Round>> {
     separated_list1(tag("; "), round)(input)
 }
 
+fn parse_game(line: &str) -> Result<Game> {
+    let (_, game) = all_consuming(game)(line)
+        .finish()
+        .map_err(|err| format!("invalid game {line:?}: {err}"))?;
+    Ok(game)
+}
+
 fn game(input: &str) -> IResult<&str, Game> {
     map(
-        tuple((tag("Game "), i32, tag(": "), rounds)),
+        tuple((tag("Game "), u32, tag(": "), rounds)),
         |(_, id, _, rounds)| Game { id, rounds },
     )(input)
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_colour() -> Result<()> {
-        assert_eq!(cube("red")?.1, Cube::Red);
-        assert_eq!(cube("green")?.1, Cube::Green);
-        assert_eq!(cube("blue")?.1, Cube::Blue);
+        assert_eq!(cube("red")?.1, "red");
+        assert_eq!(cube("green")?.1, "green");
+        assert_eq!(cube("blue")?.1, "blue");
+        assert_eq!(cube("purple, 1 red")?, (", 1 red", "purple"));
         Ok(())
     }
 
     #[test]
     fn test_colour_count() -> Result<()> {
-        assert_eq!(cube_count("1 red")?.1, (Cube::Red, 1));
-        assert_eq!(cube_count("2 green")?.1, (Cube::Green, 2));
-        assert_eq!(cube_count("3 blue")?.1, (Cube::Blue, 3));
+        assert_eq!(cube_count("1 red")?.1, ("red", 1));
+        assert_eq!(cube_count("2 green")?.1, ("green", 2));
+        assert_eq!(cube_count("3 blue")?.1, ("blue", 3));
+        assert_eq!(cube_count("4 purple")?.1, ("purple", 4));
         Ok(())
     }
 
@@ -159,28 +297,13 @@ mod tests {
     fn test_round() -> Result<()> {
         assert_eq!(
             round("3 blue, 4 red")?.1,
-            Round {
-                red: 4,
-                green: 0,
-                blue: 3,
-            }
+            Round::from([("red", 4), ("blue", 3)])
         );
         assert_eq!(
             round("1 red, 2 green, 6 blue")?.1,
-            Round {
-                red: 1,
-                green: 2,
-                blue: 6,
-            }
-        );
-        assert_eq!(
-            round("2 green")?.1,
-            Round {
-                red: 0,
-                green: 2,
-                blue: 0,
-            }
+            Round::from([("red", 1), ("green", 2), ("blue", 6)])
         );
+        assert_eq!(round("2 green")?.1, Round::from([("green", 2)]));
         Ok(())
     }
 
@@ -189,21 +312,9 @@ mod tests {
         assert_eq!(
             rounds("3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green")?.1,
             vec![
-                Round {
-                    red: 4,
-                    green: 0,
-                    blue: 3,
-                },
-                Round {
-                    red: 1,
-                    green: 2,
-                    blue: 6,
-                },
-                Round {
-                    red: 0,
-                    green: 2,
-                    blue: 0,
-                },
+                Round::from([("red", 4), ("blue", 3)]),
+                Round::from([("red", 1), ("green", 2), ("blue", 6)]),
+                Round::from([("green", 2)]),
             ]
         );
         Ok(())
@@ -216,27 +327,22 @@ mod tests {
             Game {
                 id: 1,
                 rounds: vec![
-                    Round {
-                        red: 4,
-                        green: 0,
-                        blue: 3,
-                    },
-                    Round {
-                        red: 1,
-                        green: 2,
-                        blue: 6,
-                    },
-                    Round {
-                        red: 0,
-                        green: 2,
-                        blue: 0,
-                    },
+                    Round::from([("red", 4), ("blue", 3)]),
+                    Round::from([("red", 1), ("green", 2), ("blue", 6)]),
+                    Round::from([("green", 2)]),
                 ]
             }
         );
         Ok(())
     }
 
+    #[test]
+    fn test_parse_game() {
+        assert!(parse_game("Game 1: 3 blue, 4 red").is_ok());
+        assert!(parse_game("Game 1: 3 blue junk").is_err());
+        assert!(parse_game("Game 1: 3 blue;").is_err());
+    }
+
     #[test]
     fn test_game_is_possible() {
         let inputs = [
@@ -251,11 +357,7 @@ mod tests {
             .map(|input| game(input).unwrap().1)
             .collect::<Vec<Game>>();
 
-        let cube_count = CubeCount {
-            red: 12,
-            green: 13,
-            blue: 14,
-        };
+        let cube_count = CubeCount::from([("red", 12), ("green", 13), ("blue", 14)]);
 
         assert!(games[0].is_possible(&cube_count));
         assert!(games[1].is_possible(&cube_count));
@@ -280,44 +382,115 @@ mod tests {
 
         assert_eq!(
             games[0].min_cube_count(),
-            CubeCount {
-                red: 4,
-                green: 2,
-                blue: 6
-            }
+            CubeCount::from([("red", 4), ("green", 2), ("blue", 6)])
         );
         assert_eq!(
             games[1].min_cube_count(),
-            CubeCount {
-                red: 1,
-                green: 3,
-                blue: 4
-            }
+            CubeCount::from([("red", 1), ("green", 3), ("blue", 4)])
         );
         assert_eq!(
             games[2].min_cube_count(),
-            CubeCount {
-                red: 20,
-                green: 13,
-                blue: 6
-            }
+            CubeCount::from([("red", 20), ("green", 13), ("blue", 6)])
         );
         assert_eq!(
             games[3].min_cube_count(),
-            CubeCount {
-                red: 14,
-                green: 3,
-                blue: 15
-            }
+            CubeCount::from([("red", 14), ("green", 3), ("blue", 15)])
         );
         assert_eq!(
             games[4].min_cube_count(),
-            CubeCount {
-                red: 6,
-                green: 3,
-                blue: 2
-            }
+            CubeCount::from([("red", 6), ("green", 3), ("blue", 2)])
+        );
+    }
+
+    #[test]
+    fn test_arbitrary_colours() {
+        let game = game("Game 7: 2 purple, 1 red; 5 purple, 3 orange")
+            .unwrap()
+            .1;
+
+        assert!(game.is_possible(&"5 purple, 3 orange, 1 red".parse().unwrap()));
+        assert!(!game.is_possible(&"5 purple, 1 red".parse().unwrap()));
+        assert_eq!(
+            game.min_cube_count(),
+            CubeCount::from([("purple", 5), ("red", 1), ("orange", 3)])
+        );
+        assert_eq!(game.min_cube_count().power(["purple", "red"]), Some(5));
+        assert_eq!(
+            game.min_cube_count().power(["purple", "red", "orange"]),
+            Some(15)
+        );
+        assert_eq!(game.min_cube_count().power(["purple", "blue"]), Some(0));
+    }
+
+    #[test]
+    fn test_parse_bag() {
+        assert_eq!(
+            "12 red, 13 green, 14 blue".parse(),
+            Ok(CubeCount::from([("red", 12), ("green", 13), ("blue", 14)]))
+        );
+        assert!("12 red, 13".parse::<CubeCount>().is_err());
+        assert!("red".parse::<CubeCount>().is_err());
+    }
+
+    fn example_games() -> Games {
+        [
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green",
+            "Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue",
+            "Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red",
+            "Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red",
+            "Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green",
+        ]
+        .iter()
+        .map(|input| game(input).unwrap().1)
+        .collect()
+    }
+
+    #[test]
+    fn test_min_limit_admitting() {
+        // Max red draws are 4, 1, 20, 14, 6 and max green draws 2, 3, 13, 3, 3.
+        let games = example_games();
+        assert_eq!(games.min_limit_admitting("red", 0), Some(0));
+        assert_eq!(games.min_limit_admitting("red", 1), Some(1));
+        assert_eq!(games.min_limit_admitting("red", 3), Some(6));
+        assert_eq!(games.min_limit_admitting("red", 5), Some(20));
+        assert_eq!(games.min_limit_admitting("red", 6), None);
+        assert_eq!(games.min_limit_admitting("green", 2), None);
+        assert_eq!(games.min_limit_admitting("green", 4), Some(3));
+        assert_eq!(games.min_limit_admitting("purple", 0), None);
+        assert_eq!(games.min_limit_admitting("purple", 5), Some(0));
+    }
+
+    #[test]
+    fn test_max_draws() {
+        let max_draws = example_games().max_draws();
+        assert_eq!(max_draws.len(), 5);
+        assert_eq!(
+            max_draws[&3],
+            CubeCount::from([("red", 20), ("green", 13), ("blue", 6)])
+        );
+    }
+
+    #[test]
+    fn test_round_size_distribution() {
+        let sizes = example_games().round_size_distribution();
+        assert_eq!(sizes.values().sum::<usize>(), 14);
+        assert_eq!(sizes[&2], 2);
+        assert_eq!(sizes[&3], 1);
+        assert_eq!(sizes[&34], 1);
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert!(round("4294967295 red").is_ok());
+        assert!(round("4294967295 red, 1 red").is_err());
+        assert!(round("4294967296 red").is_err());
+
+        let huge = CubeCount::from([("red", u32::MAX), ("green", u32::MAX), ("blue", 2)]);
+        assert_eq!(
+            huge.power(["red", "green"]),
+            Some(u64::from(u32::MAX).pow(2))
         );
+        assert_eq!(huge.power(["red", "green", "blue"]), None);
     }
 
     #[test]
@@ -1,7 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use nom::{
-    branch::alt, bytes::complete::tag, character::complete::digit1, combinator::opt, IResult,
+    branch::alt,
+    character::complete::{char, digit1, satisfy},
+    combinator::{all_consuming, opt, recognize},
+    sequence::preceded,
+    IResult,
 };
 
 use crate::{Result, Solution};
@@ -10,11 +14,32 @@ trait HasRow {
     fn row(&self) -> i32;
 }
 
+/// Characters with a special meaning in a schematic. Every other character
+/// that isn't a digit or whitespace is a symbol.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-struct Symbol {
-    is_asterisk: bool,
-    row: i32,
-    col: i32,
+pub struct SchematicConfig {
+    pub background: char,
+    pub gear: char,
+    /// Whether a `-` directly before digits is part of the number rather
+    /// than a symbol of its own.
+    pub signed_numbers: bool,
+}
+
+impl Default for SchematicConfig {
+    fn default() -> Self {
+        Self {
+            background: '.',
+            gear: '*',
+            signed_numbers: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Symbol {
+    pub kind: char,
+    pub row: i32,
+    pub col: i32,
 }
 
 impl HasRow for Symbol {
@@ -24,10 +49,12 @@ impl HasRow for Symbol {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-struct Number {
-    value: i32,
-    row: i32,
-    col: i32,
+pub struct Number {
+    pub value: i32,
+    pub row: i32,
+    pub col: i32,
+    /// Number of columns the number spans, including any sign or leading zeros.
+    pub width: i32,
 }
 
 impl HasRow for Number {
@@ -38,35 +65,96 @@ impl HasRow for Number {
 
 type Schematic = (Vec<Number>, Vec<Symbol>);
 
-pub fn solution() -> Result<Solution<i32>> {
+/// How many adjacent numbers a symbol needs to count as a gear.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Neighbours {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl Neighbours {
+    fn admits(&self, count: usize) -> bool {
+        match self {
+            Neighbours::Exactly(n) => count == *n,
+            Neighbours::AtLeast(n) => count >= *n,
+        }
+    }
+}
+
+/// How the numbers adjacent to a gear combine into its value.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reduction {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Reduction {
+    /// `None` if the value overflows `i64`, which any number of neighbours
+    /// allows.
+    fn reduce(&self, numbers: &[Number]) -> Option<i64> {
+        let mut values = numbers.iter().map(|number| i64::from(number.value));
+        match self {
+            Reduction::Product => values.try_fold(1, i64::checked_mul),
+            Reduction::Sum => values.try_fold(0, i64::checked_add),
+            Reduction::Max => Some(values.max().unwrap_or(0)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GearRule {
+    pub symbol: char,
+    pub neighbours: Neighbours,
+    pub reduction: Reduction,
+}
+
+impl GearRule {
+    /// The part 2 rule: a `symbol` next to exactly two numbers, multiplied.
+    pub fn ratio(symbol: char) -> Self {
+        Self {
+            symbol,
+            neighbours: Neighbours::Exactly(2),
+            reduction: Reduction::Product,
+        }
+    }
+}
+
+/// A symbol matched by a `GearRule`, with the numbers that made it match.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Gear {
+    pub symbol: Symbol,
+    pub numbers: Vec<Number>,
+    /// The reduced value, or `None` if it overflowed.
+    pub value: Option<i64>,
+}
+
+pub fn solution() -> Result<Solution<i64>> {
+    solution_with_config(&SchematicConfig::default())
+}
+
+pub fn solution_with_config(config: &SchematicConfig) -> Result<Solution<i64>> {
     let input = include_str!("../input/3.txt");
-    let (numbers, symbols) = parse_schematic(input)?.1;
+    let (numbers, symbols) = parse_schematic(input, config)?;
 
     let num_index = build_index(&numbers);
     let sym_index = build_index(&symbols);
 
-    let part_1: i32 = numbers
+    let part_1: i64 = numbers
         .iter()
         .filter_map(|number| {
             if !adjacent_symbols(&sym_index, number).is_empty() {
-                Some(number.value)
+                Some(i64::from(number.value))
             } else {
                 None
             }
         })
         .sum();
 
-    let part_2: i32 = symbols
+    let part_2 = find_gears(&GearRule::ratio(config.gear), &num_index, &symbols)
         .iter()
-        .filter_map(|symbol| {
-            let adjacent_numbers = adjacent_numbers(&num_index, symbol);
-            if symbol.is_asterisk && adjacent_numbers.len() == 2 {
-                Some(adjacent_numbers[0].value * adjacent_numbers[1].value)
-            } else {
-                None
-            }
-        })
-        .sum();
+        .try_fold(0_i64, |sum, gear| sum.checked_add(gear.value?))
+        .ok_or("gear ratios overflowed")?;
 
     Ok(Solution {
         day: 3,
@@ -75,6 +163,139 @@ pub fn solution() -> Result<Solution<i32>> {
     })
 }
 
+/// The distinct symbol characters that appear in the schematic.
+pub fn symbol_kinds(config: &SchematicConfig) -> Result<BTreeSet<char>> {
+    let input = include_str!("../input/3.txt");
+    let (_, symbols) = parse_schematic(input, config)?;
+    Ok(kinds(&symbols))
+}
+
+fn kinds(symbols: &[Symbol]) -> BTreeSet<char> {
+    symbols.iter().map(|symbol| symbol.kind).collect()
+}
+
+/// Every symbol in the schematic that matches `rule`.
+pub fn gears(rule: &GearRule, config: &SchematicConfig) -> Result<Vec<Gear>> {
+    let input = include_str!("../input/3.txt");
+    let (numbers, symbols) = parse_schematic(input, config)?;
+    Ok(find_gears(rule, &build_index(&numbers), &symbols))
+}
+
+/// How `explain` highlights the schematic.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RenderStyle {
+    /// Colour part numbers green, other numbers red and gears yellow.
+    Ansi,
+    /// Follow each row with a marker row: `^` under part numbers, `x` under
+    /// other numbers and `G` under gears.
+    Plain,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Highlight {
+    PartNumber,
+    OtherNumber,
+    Gear,
+}
+
+impl Highlight {
+    fn ansi_colour(&self) -> &'static str {
+        match self {
+            Highlight::PartNumber => "\x1b[32m",
+            Highlight::OtherNumber => "\x1b[31m",
+            Highlight::Gear => "\x1b[1;33m",
+        }
+    }
+
+    fn marker(&self) -> char {
+        match self {
+            Highlight::PartNumber => '^',
+            Highlight::OtherNumber => 'x',
+            Highlight::Gear => 'G',
+        }
+    }
+}
+
+/// The schematic with the numbers and gears that went into the answer
+/// highlighted.
+pub fn explain(config: &SchematicConfig, style: RenderStyle) -> Result<String> {
+    let input = include_str!("../input/3.txt");
+    render(input, config, style)
+}
+
+fn render(input: &str, config: &SchematicConfig, style: RenderStyle) -> Result<String> {
+    let (numbers, symbols) = parse_schematic(input, config)?;
+    let num_index = build_index(&numbers);
+    let sym_index = build_index(&symbols);
+
+    let mut highlights: HashMap<(i32, i32), Highlight> = HashMap::new();
+    for number in &numbers {
+        let highlight = if adjacent_symbols(&sym_index, number).is_empty() {
+            Highlight::OtherNumber
+        } else {
+            Highlight::PartNumber
+        };
+        for col in number.col..number.col + number.width {
+            highlights.insert((number.row, col), highlight);
+        }
+    }
+    for gear in find_gears(&GearRule::ratio(config.gear), &num_index, &symbols) {
+        highlights.insert((gear.symbol.row, gear.symbol.col), Highlight::Gear);
+    }
+
+    let mut output = String::new();
+    for (row, line) in input.lines().enumerate() {
+        let highlight_at = |col: usize| highlights.get(&(row as i32, col as i32));
+        match style {
+            RenderStyle::Ansi => {
+                for (col, c) in line.chars().enumerate() {
+                    match highlight_at(col) {
+                        Some(highlight) => {
+                            output += &format!("{}{c}\x1b[0m", highlight.ansi_colour())
+                        }
+                        None => output.push(c),
+                    }
+                }
+                output.push('\n');
+            }
+            RenderStyle::Plain => {
+                output += line;
+                output.push('\n');
+                let markers = (0..line.chars().count())
+                    .map(|col| highlight_at(col).map_or(' ', Highlight::marker))
+                    .collect::<String>();
+                if !markers.trim_end().is_empty() {
+                    output += markers.trim_end();
+                    output.push('\n');
+                }
+            }
+        }
+    }
+    Ok(output)
+}
+
+fn find_gears(rule: &GearRule, number_index: &RowIndex<Number>, symbols: &[Symbol]) -> Vec<Gear> {
+    symbols
+        .iter()
+        .filter(|symbol| symbol.kind == rule.symbol)
+        .filter_map(|symbol| {
+            let numbers = adjacent_numbers(number_index, symbol)
+                .into_iter()
+                .copied()
+                .collect::<Vec<_>>();
+            if rule.neighbours.admits(numbers.len()) {
+                Some(Gear {
+                    symbol: *symbol,
+                    value: rule.reduction.reduce(&numbers),
+                    numbers,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
 fn build_index<T: HasRow>(items: &[T]) -> RowIndex<'_, T> {
     items.iter().fold(RowIndex::new(), |mut index, item| {
         index.insert(item.row(), item);
@@ -89,7 +310,7 @@ fn adjacent_symbols<'a>(
     let above_row = number.row - 1;
     let below_row = number.row + 1;
     let left_col = number.col - 1;
-    let right_col = number.col + num_digits(number.value);
+    let right_col = number.col + number.width;
     symbol_index
         .get_items_from_rows(above_row, below_row)
         .into_iter()
@@ -107,7 +328,7 @@ fn adjacent_numbers<'a>(number_index: &'a RowIndex<Number>, symbol: &Symbol) ->
         .into_iter()
         .filter(|number| {
             let number_left_col = number.col;
-            let number_right_col = number.col + num_digits(number.value) - 1;
+            let number_right_col = number.col + number.width - 1;
             number_right_col >= left_col && number_left_col <= right_col
         })
         .collect()
@@ -140,60 +361,61 @@ impl<'a, T> RowIndex<'a, T> {
     }
 }
 
-fn num_digits(number: i32) -> i32 {
-    let mut num_digits = 0;
-    let mut number = number;
-    if number == 0 {
-        return 1;
-    }
-    while number > 0 {
-        number /= 10;
-        num_digits += 1;
+fn symbol(background: char) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |input| {
+        recognize(satisfy(|c| {
+            c != background && !c.is_ascii_digit() && !c.is_whitespace()
+        }))(input)
     }
-    num_digits
 }
 
-fn symbol(input: &str) -> IResult<&str, &str> {
-    alt((
-        tag("@"),
-        tag("#"),
-        tag("$"),
-        tag("%"),
-        tag("&"),
-        tag("*"),
-        tag("-"),
-        tag("+"),
-        tag("="),
-        tag("/"),
-    ))(input)
+fn number(signed: bool) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |input| {
+        if signed {
+            recognize(preceded(opt(char('-')), digit1))(input)
+        } else {
+            digit1(input)
+        }
+    }
 }
 
-fn number_or_symbol(input: &str) -> IResult<&str, &str> {
-    alt((digit1, symbol))(input)
+fn number_or_symbol(config: SchematicConfig) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |input| alt((number(config.signed_numbers), symbol(config.background)))(input)
 }
 
-fn parse_schematic(input: &str) -> IResult<&str, Schematic> {
+/// Fails on a number too large for `i32`. Splitting it into a symbol and a
+/// shorter number would put both next to the wrong neighbours.
+fn parse_schematic(input: &str, config: &SchematicConfig) -> Result<Schematic> {
     let mut numbers = Vec::new();
     let mut symbols = Vec::new();
     for (row, line) in input.lines().enumerate() {
-        let items_and_cols = find_all_indexed(number_or_symbol)(line)?.1;
+        let (_, items_and_cols) =
+            find_all_indexed(number_or_symbol(*config))(line).map_err(|err| err.to_owned())?;
         for (item, col) in items_and_cols {
-            if let Ok(value) = item.parse::<i32>() {
+            if all_consuming(number(config.signed_numbers))(item).is_ok() {
+                let value = item.parse::<i32>().map_err(|_| {
+                    format!(
+                        "number {item} at row {}, column {} doesn't fit in i32",
+                        row + 1,
+                        col + 1
+                    )
+                })?;
                 numbers.push(Number {
                     value,
                     row: row as i32,
                     col: col as i32,
+                    width: item.len() as i32,
                 });
             } else {
                 symbols.push(Symbol {
-                    is_asterisk: item == "*",
+                    kind: item.chars().next().unwrap(),
                     row: row as i32,
                     col: col as i32,
                 });
             }
         }
     }
-    Ok(("", (numbers, symbols)))
+    Ok((numbers, symbols))
 }
 
 fn find_all_indexed<'a, T>(
@@ -208,14 +430,16 @@ where
         while let Ok((remainder, maybe_match)) = opt(&parser)(input) {
             if let Some(match_) = maybe_match {
                 matches.push((match_, index));
-                index += input.len() - remainder.len();
+                index += input[..input.len() - remainder.len()].chars().count();
                 input = remainder;
             } else {
-                if remainder.is_empty() {
+                // Columns count chars, not bytes, so multibyte symbols and
+                // backgrounds don't shift the rest of the row.
+                let Some(skipped) = remainder.chars().next() else {
                     break;
-                }
+                };
                 index += 1;
-                input = &remainder[1..];
+                input = &remainder[skipped.len_utf8()..];
             }
         }
         Ok(("", matches))
@@ -255,7 +479,7 @@ mod tests {
             vec![("664", 1), ("598", 5)],
         ];
 
-        let mut parser = find_all_indexed(number_or_symbol);
+        let mut parser = find_all_indexed(number_or_symbol(SchematicConfig::default()));
         for (index, line) in input.iter().enumerate() {
             let (remainder, matches) = parser(line)?;
             assert_eq!(remainder, "");
@@ -279,19 +503,247 @@ mod tests {
             ".664.598..",
         ]
         .join("\n");
-        let (numbers, symbols) = parse_schematic(&input).unwrap().1;
+        let (numbers, symbols) = parse_schematic(&input, &SchematicConfig::default()).unwrap();
 
         assert_eq!(numbers.len(), 10);
         assert_eq!(symbols.len(), 6);
     }
 
     #[test]
-    fn test_num_digits() {
-        let inputs = vec![0, 1, 10, 100, 1000, 10000];
-        let outputs = vec![1, 1, 2, 3, 4, 5];
-        for (input, output) in inputs.into_iter().zip(outputs) {
-            assert_eq!(num_digits(input), output);
-        }
+    fn test_symbol_kinds() {
+        let input = ["12?..", "..~3.", "!...^"].join("\n");
+        let (numbers, symbols) = parse_schematic(&input, &SchematicConfig::default()).unwrap();
+        assert_eq!(numbers.len(), 2);
+        assert_eq!(kinds(&symbols), BTreeSet::from(['?', '~', '!', '^']));
+    }
+
+    #[test]
+    fn test_custom_background_and_gear() {
+        let input = ["467  114  ", "   x      ", "  35  633 ", "      .   "].join("\n");
+        let config = SchematicConfig {
+            background: ' ',
+            gear: 'x',
+            ..SchematicConfig::default()
+        };
+        let (numbers, symbols) = parse_schematic(&input, &config).unwrap();
+        assert_eq!(numbers.len(), 4);
+        assert_eq!(kinds(&symbols), BTreeSet::from(['x', '.']));
+        assert_eq!(
+            adjacent_numbers(&build_index(&numbers), &symbols[0]),
+            vec![&numbers[0], &numbers[2]]
+        );
+    }
+
+    #[test]
+    fn test_find_gears() {
+        let input = [
+            "467..114..",
+            "...*......",
+            "..35..633.",
+            "......#...",
+            "617*......",
+            ".....+.58.",
+            "..592.....",
+            "......755.",
+            "...$.*....",
+            ".664.598..",
+        ]
+        .join("\n");
+        let (numbers, symbols) = parse_schematic(&input, &SchematicConfig::default()).unwrap();
+        let num_index = build_index(&numbers);
+
+        let gears = find_gears(&GearRule::ratio('*'), &num_index, &symbols);
+        assert_eq!(
+            gears,
+            vec![
+                Gear {
+                    symbol: symbols[0],
+                    numbers: vec![numbers[0], numbers[2]],
+                    value: Some(16345),
+                },
+                Gear {
+                    symbol: symbols[5],
+                    numbers: vec![numbers[7], numbers[9]],
+                    value: Some(451490),
+                },
+            ]
+        );
+
+        let rule = GearRule {
+            symbol: '*',
+            neighbours: Neighbours::AtLeast(1),
+            reduction: Reduction::Sum,
+        };
+        let values = find_gears(&rule, &num_index, &symbols)
+            .iter()
+            .map(|gear| gear.value)
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![Some(502), Some(617), Some(1353)]);
+
+        let rule = GearRule {
+            symbol: '$',
+            neighbours: Neighbours::Exactly(1),
+            reduction: Reduction::Max,
+        };
+        let values = find_gears(&rule, &num_index, &symbols)
+            .iter()
+            .map(|gear| gear.value)
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![Some(664)]);
+    }
+
+    #[test]
+    fn test_overflowing_gear() {
+        let input = ["999.999...", "...*......", "999.999..."].join("\n");
+        let (numbers, symbols) = parse_schematic(&input, &SchematicConfig::default()).unwrap();
+        let num_index = build_index(&numbers);
+        let rule = GearRule {
+            symbol: '*',
+            neighbours: Neighbours::AtLeast(1),
+            reduction: Reduction::Product,
+        };
+        let gears = find_gears(&rule, &num_index, &symbols);
+        assert_eq!(gears[0].numbers.len(), 4);
+        assert_eq!(gears[0].value, Some(999_i64.pow(4)));
+
+        let input = [
+            "999999999.999999999",
+            ".........*.........",
+            "999999999..........",
+        ]
+        .join("\n");
+        let (numbers, symbols) = parse_schematic(&input, &SchematicConfig::default()).unwrap();
+        let num_index = build_index(&numbers);
+        let gears = find_gears(&rule, &num_index, &symbols);
+        assert_eq!(gears[0].numbers.len(), 3);
+        assert_eq!(gears[0].value, None);
+    }
+
+    #[test]
+    fn test_number_too_large() {
+        let err = parse_schematic("..\n12345678901.", &SchematicConfig::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "number 12345678901 at row 2, column 1 doesn't fit in i32"
+        );
+
+        let config = SchematicConfig {
+            signed_numbers: true,
+            ..SchematicConfig::default()
+        };
+        let (numbers, symbols) = parse_schematic("-2147483648*", &config).unwrap();
+        assert_eq!(numbers[0].value, i32::MIN);
+        assert_eq!(symbols.len(), 1);
+        assert!(parse_schematic("-2147483649*", &config).is_err());
+    }
+
+    #[test]
+    fn test_render() -> Result<()> {
+        let input = ["467..114..", "...*......", "..35..633.", "......#..."].join("\n");
+        let config = SchematicConfig::default();
+
+        let expected = [
+            "467..114..",
+            "^^^  xxx",
+            "...*......",
+            "   G",
+            "..35..633.",
+            "  ^^  ^^^",
+            "......#...",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(render(&input, &config, RenderStyle::Plain)?, expected);
+
+        let ansi = render(&input, &config, RenderStyle::Ansi)?;
+        assert!(ansi.starts_with("\x1b[32m4\x1b[0m"));
+        assert!(ansi.contains("\x1b[1;33m*\x1b[0m"));
+        assert!(ansi.contains("\x1b[31m1\x1b[0m"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_number_width() {
+        let input = "0.1.10.007.-12.1000";
+        let (numbers, _) = parse_schematic(input, &SchematicConfig::default()).unwrap();
+        let values_and_widths = numbers
+            .iter()
+            .map(|number| (number.value, number.width))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values_and_widths,
+            vec![(0, 1), (1, 1), (10, 2), (7, 3), (12, 2), (1000, 4)]
+        );
+    }
+
+    #[test]
+    fn test_signed_numbers() {
+        let input = ["..-12...", "007-....", "..-.-5.."].join("\n");
+
+        let (numbers, symbols) = parse_schematic(&input, &SchematicConfig::default()).unwrap();
+        assert_eq!(numbers.len(), 3);
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(numbers[0].value, 12);
+
+        let config = SchematicConfig {
+            signed_numbers: true,
+            ..SchematicConfig::default()
+        };
+        let (numbers, symbols) = parse_schematic(&input, &config).unwrap();
+        assert_eq!(
+            numbers,
+            vec![
+                Number {
+                    value: -12,
+                    row: 0,
+                    col: 2,
+                    width: 3,
+                },
+                Number {
+                    value: 7,
+                    row: 1,
+                    col: 0,
+                    width: 3,
+                },
+                Number {
+                    value: -5,
+                    row: 2,
+                    col: 4,
+                    width: 2,
+                },
+            ]
+        );
+        assert_eq!(symbols.len(), 2);
+
+        let sym_index = build_index(&symbols);
+        assert_eq!(adjacent_symbols(&sym_index, &numbers[0]).len(), 1);
+        assert_eq!(adjacent_symbols(&sym_index, &numbers[2]).len(), 1);
+    }
+
+    #[test]
+    fn test_multibyte_symbols_and_background() {
+        let (numbers, symbols) = parse_schematic("é12..", &SchematicConfig::default()).unwrap();
+        assert_eq!(numbers[0].col, 1);
+        assert_eq!(
+            symbols,
+            vec![Symbol {
+                kind: 'é',
+                row: 0,
+                col: 0
+            }]
+        );
+        let sym_index = build_index(&symbols);
+        assert_eq!(adjacent_symbols(&sym_index, &numbers[0]).len(), 1);
+
+        let config = SchematicConfig {
+            background: '·',
+            ..SchematicConfig::default()
+        };
+        let (numbers, symbols) = parse_schematic("··12·\n····#", &config).unwrap();
+        assert_eq!(numbers[0].col, 2);
+        assert_eq!(symbols[0].col, 4);
+        let sym_index = build_index(&symbols);
+        assert_eq!(adjacent_symbols(&sym_index, &numbers[0]).len(), 1);
     }
 
     #[test]
@@ -309,7 +761,7 @@ mod tests {
             ".664.598..",
         ]
         .join("\n");
-        let (numbers, symbols) = parse_schematic(&input).unwrap().1;
+        let (numbers, symbols) = parse_schematic(&input, &SchematicConfig::default()).unwrap();
         let sym_index = build_index(&symbols);
 
         let expected = [
@@ -344,7 +796,7 @@ mod tests {
             ".664.598..",
         ]
         .join("\n");
-        let (numbers, symbols) = parse_schematic(&input).unwrap().1;
+        let (numbers, symbols) = parse_schematic(&input, &SchematicConfig::default()).unwrap();
         let num_index = build_index(&numbers);
 
         let expected = [
@@ -1,27 +1,27 @@
-use std::{collections::HashSet, iter::repeat};
+use std::{collections::HashSet, fmt::Display};
 
 use itertools::Itertools;
 use nom::{
     bytes::complete::tag,
     character::complete::{i32, space1},
-    combinator::map,
-    multi::many1,
+    combinator::{all_consuming, map},
+    multi::{fold_many1, many1},
     sequence::{preceded, tuple},
-    IResult,
+    Finish, IResult,
 };
 
-use crate::{Result, Solution};
+use crate::{Result, Solution, Strictness};
 
-pub fn solution() -> Result<Solution<i32>> {
+pub fn solution() -> Result<Solution<u64>> {
+    solution_with_strictness(Strictness::Warn)
+}
+
+pub fn solution_with_strictness(strictness: Strictness) -> Result<Solution<u64>> {
     let input = include_str!("../input/4.txt");
 
-    let part_1 = input
-        .lines()
-        .map(|line| {
-            let (_, card) = parse_card(line).unwrap();
-            compute_score(&card)
-        })
-        .sum();
+    strictness.check(&validate_cards(input))?;
+
+    let part_1 = parse_cards(input)?.iter().map(compute_score).sum();
 
     let part_2 = part_2(input)?;
 
@@ -32,42 +32,175 @@ pub fn solution() -> Result<Solution<i32>> {
     })
 }
 
-fn part_2(input: &str) -> Result<i32> {
-    let mut counts: Box<dyn Iterator<Item = i32>> = Box::new(repeat(1_i32));
-    let mut total = 0;
-    for line in input.lines() {
-        let (_, card) = parse_card(line).unwrap();
-        let matches = num_matches(&card);
-        let count = counts.next().unwrap();
+fn part_2(input: &str) -> Result<u64> {
+    let total = copy_counts(input)?
+        .into_iter()
+        .try_fold(0_u64, u64::checked_add);
+    Ok(total.ok_or("total copy count overflowed")?)
+}
 
-        let new_counts = (&mut counts).take(matches).map(|m| m + count).collect_vec();
-        counts = Box::new(new_counts.into_iter().chain(counts));
-        total += count;
+/// How many copies of each card, in input order, you end up with once every
+/// card's wins have been handed out to the cards after it.
+pub fn copy_counts(input: &str) -> Result<Vec<u64>> {
+    let matches = parse_cards(input)?.iter().map(num_matches).collect_vec();
+    Ok(cascade(&matches).ok_or("copy counts overflowed")?)
+}
+
+/// `None` if a count overflows, which cards that each win a couple of copies
+/// reach within a hundred or so cards.
+fn cascade(matches: &[usize]) -> Option<Vec<u64>> {
+    let mut counts = vec![1_u64; matches.len()];
+    for (index, matches) in matches.iter().enumerate() {
+        let count = counts[index];
+        for later in counts.iter_mut().skip(index + 1).take(*matches) {
+            *later = later.checked_add(count)?;
+        }
     }
+    Some(counts)
+}
+
+/// What happened to a single card over the course of part 2.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CardExplanation {
+    pub id: i32,
+    pub matched_numbers: Vec<i32>,
+    pub score: u64,
+    pub copies: u64,
+    /// The ids of earlier cards that won copies of this one, with how many
+    /// copies each handed out.
+    pub won_from: Vec<(i32, u64)>,
+}
+
+pub fn explain_cards(input: &str) -> Result<Vec<CardExplanation>> {
+    let cards = parse_cards(input)?;
+    let matches = cards.iter().map(num_matches).collect_vec();
+    let counts = cascade(&matches).ok_or("copy counts overflowed")?;
+
+    Ok(cards
+        .iter()
+        .enumerate()
+        .map(|(index, card)| CardExplanation {
+            id: card.id,
+            matched_numbers: card.winning_numbers.intersection(&card.your_numbers),
+            score: compute_score(card),
+            copies: counts[index],
+            won_from: (0..index)
+                .filter(|&earlier| earlier + matches[earlier] >= index)
+                .map(|earlier| (cards[earlier].id, counts[earlier]))
+                .collect(),
+        })
+        .collect())
+}
+
+/// A table of every card's matches, score and copies, and where the copies
+/// came from.
+pub fn explain() -> Result<String> {
+    let input = include_str!("../input/4.txt");
+    Ok(cascade_table(&explain_cards(input)?))
+}
+
+fn cascade_table(explanations: &[CardExplanation]) -> String {
+    let matched = explanations
+        .iter()
+        .map(|explanation| explanation.matched_numbers.iter().join(" "))
+        .collect_vec();
+    let width = matched.iter().map(String::len).max().unwrap_or(0).max(7);
 
-    Ok(total)
+    let mut table = format!(
+        "{:>5} | {:<width$} | {:>6} | {:>6} | Won from\n",
+        "Card", "Matches", "Score", "Copies"
+    );
+    for (explanation, matched) in explanations.iter().zip(matched) {
+        let won_from = explanation
+            .won_from
+            .iter()
+            .map(|(id, copies)| format!("{id} (x{copies})"))
+            .join(", ");
+        let row = format!(
+            "{:>5} | {:<width$} | {:>6} | {:>6} | {}",
+            explanation.id, matched, explanation.score, explanation.copies, won_from
+        );
+        table += row.trim_end();
+        table.push('\n');
+    }
+    table
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 struct Card {
     id: i32,
-    winning_numbers: HashSet<i32>,
-    your_numbers: HashSet<i32>,
+    winning_numbers: NumberSet,
+    your_numbers: NumberSet,
+}
+
+/// A set of card numbers. Numbers in `0..128` live in a bitset, so the usual
+/// two-digit cards never allocate; anything else spills into a hash set.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+struct NumberSet {
+    bits: u128,
+    overflow: HashSet<i32>,
+}
+
+impl NumberSet {
+    fn insert(&mut self, number: i32) {
+        match u32::try_from(number) {
+            Ok(bit) if bit < u128::BITS => self.bits |= 1 << bit,
+            _ => {
+                self.overflow.insert(number);
+            }
+        }
+    }
+
+    fn intersection_count(&self, other: &NumberSet) -> usize {
+        (self.bits & other.bits).count_ones() as usize
+            + self.overflow.intersection(&other.overflow).count()
+    }
+
+    /// The numbers in both sets, in ascending order.
+    fn intersection(&self, other: &NumberSet) -> Vec<i32> {
+        let bits = self.bits & other.bits;
+        (0..u128::BITS as i32)
+            .filter(|bit| bits & (1 << bit) != 0)
+            .chain(self.overflow.intersection(&other.overflow).copied())
+            .sorted()
+            .collect()
+    }
 }
 
-fn compute_score(card: &Card) -> i32 {
+impl FromIterator<i32> for NumberSet {
+    fn from_iter<I: IntoIterator<Item = i32>>(numbers: I) -> Self {
+        numbers
+            .into_iter()
+            .fold(NumberSet::default(), |mut set, number| {
+                set.insert(number);
+                set
+            })
+    }
+}
+
+fn compute_score(card: &Card) -> u64 {
     let matches = num_matches(card);
     if matches == 0 {
         0
     } else {
-        2_i32.pow((matches - 1) as u32)
+        2_u64.pow((matches - 1) as u32)
     }
 }
 
 fn num_matches(card: &Card) -> usize {
-    card.winning_numbers
-        .intersection(&card.your_numbers)
-        .count()
+    card.winning_numbers.intersection_count(&card.your_numbers)
+}
+
+fn parse_cards(input: &str) -> Result<Vec<Card>> {
+    input
+        .lines()
+        .map(|line| {
+            let (_, card) = parse_card(line)
+                .finish()
+                .map_err(|err| format!("invalid card {line:?}: {err}"))?;
+            Ok(card)
+        })
+        .collect()
 }
 
 fn parse_card(input: &str) -> IResult<&str, Card> {
@@ -83,16 +216,176 @@ fn parse_card(input: &str) -> IResult<&str, Card> {
         )),
         |(_, _, id, _, winning_numbers, _, your_numbers)| Card {
             id,
-            winning_numbers: winning_numbers.into_iter().collect(),
-            your_numbers: your_numbers.clone().into_iter().collect(),
+            winning_numbers,
+            your_numbers,
         },
     )(input)
 }
 
-fn numbers(input: &str) -> IResult<&str, Vec<i32>> {
+/// Which of a card's two lists of numbers an issue was found in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NumberList {
+    Winning,
+    Yours,
+}
+
+/// A problem with the scratchcards that parsing into sets would hide.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CardIssue {
+    DuplicateNumber {
+        card: i32,
+        list: NumberList,
+        number: i32,
+    },
+    /// The card at `line` doesn't have the id following the previous card's.
+    UnexpectedId {
+        line: usize,
+        expected: i32,
+        found: i32,
+    },
+    /// The card wins copies of more cards than follow it.
+    WinsPastEnd {
+        card: i32,
+        matches: usize,
+        remaining: usize,
+    },
+    /// The line at `line` isn't a card at all.
+    Malformed { line: usize, error: String },
+}
+
+impl Display for CardIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardIssue::DuplicateNumber { card, list, number } => {
+                let list = match list {
+                    NumberList::Winning => "winning numbers",
+                    NumberList::Yours => "your numbers",
+                };
+                write!(f, "card {card}: {number} appears more than once in {list}")
+            }
+            CardIssue::UnexpectedId {
+                line,
+                expected,
+                found,
+            } if found > expected => write!(
+                f,
+                "line {line}: expected card {expected} but found card {found}, missing {}",
+                (*expected..*found).join(", ")
+            ),
+            CardIssue::UnexpectedId {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {line}: expected card {expected} but found card {found}, out of order"
+            ),
+            CardIssue::WinsPastEnd {
+                card,
+                matches,
+                remaining,
+            } => write!(
+                f,
+                "card {card}: wins copies of {matches} cards but only {remaining} follow it"
+            ),
+            CardIssue::Malformed { line, error } => {
+                write!(f, "line {line}: malformed card: {error}")
+            }
+        }
+    }
+}
+
+pub fn validate_cards(input: &str) -> Vec<CardIssue> {
+    let lines = input.lines().collect_vec();
+
+    let mut issues = vec![];
+    let mut expected_id = 1;
+    for (index, line) in lines.iter().enumerate() {
+        let (id, winning_numbers, your_numbers) = match all_consuming(card_lists)(line).finish() {
+            Ok((_, card)) => card,
+            Err(err) => {
+                issues.push(CardIssue::Malformed {
+                    line: index + 1,
+                    error: err.to_string(),
+                });
+                // Assume the line held the next card, so later ids still line up.
+                expected_id += 1;
+                continue;
+            }
+        };
+        for (list, numbers) in [
+            (NumberList::Winning, &winning_numbers),
+            (NumberList::Yours, &your_numbers),
+        ] {
+            issues.extend(
+                numbers
+                    .iter()
+                    .duplicates()
+                    .map(|number| CardIssue::DuplicateNumber {
+                        card: id,
+                        list,
+                        number: *number,
+                    }),
+            );
+        }
+
+        if id != expected_id {
+            issues.push(CardIssue::UnexpectedId {
+                line: index + 1,
+                expected: expected_id,
+                found: id,
+            });
+        }
+        expected_id = id + 1;
+
+        let matches = winning_numbers
+            .iter()
+            .unique()
+            .filter(|number| your_numbers.contains(number))
+            .count();
+        let remaining = lines.len() - index - 1;
+        if matches > remaining {
+            issues.push(CardIssue::WinsPastEnd {
+                card: id,
+                matches,
+                remaining,
+            });
+        }
+    }
+    issues
+}
+
+/// A card's id and its two lists of numbers exactly as written.
+fn card_lists(input: &str) -> IResult<&str, (i32, Vec<i32>, Vec<i32>)> {
+    map(
+        tuple((
+            tag("Card"),
+            space1,
+            i32,
+            tag(":"),
+            number_list,
+            tag(" |"),
+            number_list,
+        )),
+        |(_, _, id, _, winning_numbers, _, your_numbers)| (id, winning_numbers, your_numbers),
+    )(input)
+}
+
+fn number_list(input: &str) -> IResult<&str, Vec<i32>> {
     many1(preceded(space1, i32))(input)
 }
 
+fn numbers(input: &str) -> IResult<&str, NumberSet> {
+    fold_many1(
+        preceded(space1, i32),
+        NumberSet::default,
+        |mut set, number| {
+            set.insert(number);
+            set
+        },
+    )(input)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -101,7 +394,7 @@ mod tests {
     fn test_numbers() -> Result<()> {
         let input = " 1 21 53 59 44";
         let (_, numbers) = numbers(input)?;
-        assert_eq!(numbers, vec![1, 21, 53, 59, 44]);
+        assert_eq!(numbers, NumberSet::from_iter([1, 21, 53, 59, 44]));
         Ok(())
     }
 
@@ -113,13 +406,26 @@ mod tests {
             card,
             Card {
                 id: 3,
-                winning_numbers: HashSet::from([1, 21, 53, 59, 44]),
-                your_numbers: HashSet::from([69, 82, 63, 72, 16, 21, 14, 1]),
+                winning_numbers: NumberSet::from_iter([1, 21, 53, 59, 44]),
+                your_numbers: NumberSet::from_iter([69, 82, 63, 72, 16, 21, 14, 1]),
             }
         );
         Ok(())
     }
 
+    #[test]
+    fn test_number_set() {
+        let a = NumberSet::from_iter([0, 5, 99, 127, 128, 1000, -3]);
+        let b = NumberSet::from_iter([5, 6, 127, 1000, -3, 2000]);
+        assert_eq!(a.overflow, HashSet::from([128, 1000, -3]));
+        assert_eq!(a.intersection_count(&b), 4);
+        assert_eq!(a.intersection(&b), vec![-3, 5, 127, 1000]);
+        assert_eq!(
+            NumberSet::from_iter([1, 1, 2]),
+            NumberSet::from_iter([2, 1])
+        );
+    }
+
     #[test]
     fn test_num_matches() {
         let input = [
@@ -154,9 +460,243 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_copy_counts() -> Result<()> {
+        let input = [
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+            "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+            "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
+            "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+            "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+        ]
+        .join("\n");
+        assert_eq!(copy_counts(&input)?, vec![1, 2, 4, 8, 14, 1]);
+        assert_eq!(part_2(&input)?, 30);
+
+        let err = copy_counts("Card 1: 41 48 | 83 86\nCard 2 13 | 61").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid card \"Card 2 13 | 61\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_explain_cards() -> Result<()> {
+        let input = [
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+            "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+            "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
+            "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+            "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+        ]
+        .join("\n");
+        let explanations = explain_cards(&input)?;
+
+        assert_eq!(
+            explanations[0],
+            CardExplanation {
+                id: 1,
+                matched_numbers: vec![17, 48, 83, 86],
+                score: 8,
+                copies: 1,
+                won_from: vec![],
+            }
+        );
+        assert_eq!(
+            explanations[4],
+            CardExplanation {
+                id: 5,
+                matched_numbers: vec![],
+                score: 0,
+                copies: 14,
+                won_from: vec![(1, 1), (3, 4), (4, 8)],
+            }
+        );
+        assert_eq!(explanations[5].won_from, vec![]);
+
+        let table = cascade_table(&explanations);
+        assert_eq!(table.lines().count(), 7);
+        assert_eq!(
+            table.lines().nth(5),
+            Some("    5 |             |      0 |     14 | 1 (x1), 3 (x4), 4 (x8)")
+        );
+
+        let err = explain_cards("Card 1: 41 48 | 83 86\nCard 2 13 | 61").unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("invalid card \"Card 2 13 | 61\""));
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_cards() {
+        let valid = [
+            "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53",
+            "Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19",
+            "Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1",
+            "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83",
+            "Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36",
+            "Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11",
+        ]
+        .join("\n");
+        assert_eq!(validate_cards(&valid), vec![]);
+
+        let invalid = [
+            "Card 1: 41 41 83 | 83 86  6 86",
+            "Card 3: 13 32 20 | 61 30 68",
+            "Card 2:  1 21 53 | 69 21  1",
+        ]
+        .join("\n");
+        assert_eq!(
+            validate_cards(&invalid),
+            vec![
+                CardIssue::DuplicateNumber {
+                    card: 1,
+                    list: NumberList::Winning,
+                    number: 41,
+                },
+                CardIssue::DuplicateNumber {
+                    card: 1,
+                    list: NumberList::Yours,
+                    number: 86,
+                },
+                CardIssue::UnexpectedId {
+                    line: 2,
+                    expected: 2,
+                    found: 3,
+                },
+                CardIssue::UnexpectedId {
+                    line: 3,
+                    expected: 4,
+                    found: 2,
+                },
+                CardIssue::WinsPastEnd {
+                    card: 2,
+                    matches: 2,
+                    remaining: 0,
+                },
+            ]
+        );
+        assert_eq!(
+            validate_cards(&invalid)[2].to_string(),
+            "line 2: expected card 2 but found card 3, missing 2"
+        );
+
+        let malformed = [
+            "Card 1: 41 48 | 83 86",
+            "Card 2 13 32 | 61 30",
+            "Card 3: 1 21 | 69 82 junk",
+            "Card 4: 5 | 6",
+        ]
+        .join("\n");
+        let issues = validate_cards(&malformed);
+        assert_eq!(
+            issues.iter().map(ToString::to_string).collect_vec(),
+            vec![
+                "line 2: malformed card: error Tag at:  13 32 | 61 30",
+                "line 3: malformed card: error Eof at:  junk",
+            ]
+        );
+        assert!(Strictness::Deny.check(&issues).is_err());
+    }
+
+    #[test]
+    fn test_part_2_many_cards() -> Result<()> {
+        // Every card wins one copy of the next, except every tenth which wins
+        // nothing, so counts climb 1..=10 and then reset.
+        let input = (1..=100_000)
+            .map(|id| {
+                if id % 10 == 0 {
+                    format!("Card {id}: 1 2 3 | 4 5 6")
+                } else {
+                    format!("Card {id}: 1 2 3 | 3 4 5")
+                }
+            })
+            .join("\n");
+        let counts = copy_counts(&input)?;
+        assert_eq!(counts.len(), 100_000);
+        assert_eq!(counts[..10], [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(counts[99_990..], [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(part_2(&input)?, 550_000);
+
+        // Winning two copies each makes the counts grow like Fibonacci
+        // numbers, past u64 well before the last card.
+        let input = (1..=200)
+            .map(|id| format!("Card {id}: 1 2 3 | 1 2 4"))
+            .join("\n");
+        let err = copy_counts(&input).unwrap_err();
+        assert_eq!(err.to_string(), "copy counts overflowed");
+        assert!(part_2(&input).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_solution() -> Result<()> {
-        assert_eq!(solution()?, Solution { day: 4, part_1: 23847, part_2: 8570000 });
+        assert_eq!(
+            solution()?,
+            Solution {
+                day: 4,
+                part_1: 23847,
+                part_2: 8570000
+            }
+        );
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod benches {
+    extern crate test;
+
+    use super::*;
+    use test::Bencher;
+
+    fn synthetic_cards() -> String {
+        let mut seed: u64 = 4;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) % 100
+        };
+        (1..=10_000)
+            .map(|id| {
+                let winning = (0..10).map(|_| format!("{:2}", next())).join(" ");
+                let yours = (0..25).map(|_| format!("{:2}", next())).join(" ");
+                format!("Card {id}: {winning} | {yours}")
+            })
+            .join("\n")
+    }
+
+    // The representation cards had before `NumberSet`.
+    fn hash_set_card(input: &str) -> IResult<&str, (HashSet<i32>, HashSet<i32>)> {
+        map(card_lists, |(_, winning_numbers, your_numbers)| {
+            (
+                winning_numbers.into_iter().collect(),
+                your_numbers.into_iter().collect(),
+            )
+        })(input)
+    }
+
+    fn hash_set_matches(input: &str) -> usize {
+        let (_, (winning_numbers, your_numbers)) = hash_set_card(input).unwrap();
+        winning_numbers.intersection(&your_numbers).count()
+    }
+
+    #[bench]
+    fn bench_hash_set_matches(bencher: &mut Bencher) {
+        let input = synthetic_cards();
+        bencher.iter(|| input.lines().map(hash_set_matches).sum::<usize>());
+    }
+
+    #[bench]
+    fn bench_number_set_matches(bencher: &mut Bencher) {
+        let input = synthetic_cards();
+        bencher.iter(|| {
+            input
+                .lines()
+                .map(|line| num_matches(&parse_card(line).unwrap().1))
+                .sum::<usize>()
+        });
+    }
+}
@@ -1,37 +1,77 @@
 use nom::{
-    branch::alt,
     bytes::complete::tag,
-    character::complete::{char, newline, u64},
-    combinator::map,
+    character::complete::{alpha1, char, newline, u64},
+    combinator::{all_consuming, map, opt},
     multi::{many1, separated_list1},
-    sequence::{preceded, terminated},
+    sequence::{preceded, separated_pair, terminated, tuple},
     Finish, IResult,
 };
 
+use std::{fmt::Display, ops::Range, str::FromStr};
+
+use crate::{Result, Solution, Strictness};
 use rayon::prelude::*;
-use crate::{Result, Solution};
+
+/// How part 2 searches for the lowest location.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Part2Strategy {
+    /// Map every seed in the seed ranges forwards to its location.
+    Forward,
+    /// Count locations up from zero until one maps back into a seed range.
+    Inverse,
+    /// Collapse the almanac into one piecewise map and look up each range.
+    Composed,
+}
+
+impl FromStr for Part2Strategy {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "forward" => Ok(Part2Strategy::Forward),
+            "inverse" => Ok(Part2Strategy::Inverse),
+            "composed" => Ok(Part2Strategy::Composed),
+            _ => Err(format!(
+                "unknown strategy {input:?}, expected forward, inverse or composed"
+            )),
+        }
+    }
+}
 
 pub fn solution() -> Result<Solution<u64>> {
+    solution_with_options(Part2Strategy::Composed, Strictness::Warn)
+}
+
+/// Solves with the given part 2 strategy, warning about or rejecting category
+/// maps whose rows overlap depending on `strictness`.
+pub fn solution_with_options(
+    strategy: Part2Strategy,
+    strictness: Strictness,
+) -> Result<Solution<u64>> {
     let input = include_str!("../input/5.txt");
 
     let (remainder, seeds) = seeds(input)?;
 
-    let almanac = Almanac::from(remainder);
+    let almanac = Almanac::try_from(remainder)?;
+    strictness.check(&almanac.ambiguities())?;
+    let composed = almanac.composed();
 
     let part_1 = seeds
         .iter()
-        .map(|seed| almanac.get_location(*seed))
+        .map(|seed| composed.get(*seed))
         .min()
-        .unwrap();
+        .ok_or("no seeds to plant")?;
 
-    let part_2: u64 = seeds
-        .chunks(2)
-        .map(|pair| {
-            let [range_start, range_length] = pair else { panic!() };
-            (*range_start..(*range_start+*range_length)).into_par_iter().map(|seed| {
-                almanac.get_location(seed)
-            }).min().unwrap()
-        }).min().unwrap();
+    let seed_ranges = seed_ranges(&seeds);
+    let part_2 = match strategy {
+        Part2Strategy::Forward => lowest_location_forward(&almanac, &seed_ranges),
+        Part2Strategy::Inverse => lowest_location_inverse(&almanac, &seed_ranges),
+        Part2Strategy::Composed => seed_ranges
+            .iter()
+            .filter_map(|seeds| composed.min_over(seeds.clone()))
+            .min(),
+    }
+    .ok_or("no seeds to plant")?;
 
     Ok(Solution {
         day: 5,
@@ -40,40 +80,501 @@ pub fn solution() -> Result<Solution<u64>> {
     })
 }
 
-struct Almanac {
+/// The whole almanac collapsed into a single seed-to-location table.
+pub fn composed_table() -> Result<String> {
+    let input = include_str!("../input/5.txt");
+    let (remainder, _) = seeds(input)?;
+    Ok(Almanac::try_from(remainder)?.composed().to_string())
+}
+
+/// The overlaps, gaps and identity regions of every category map.
+pub fn analysis() -> Result<String> {
+    let input = include_str!("../input/5.txt");
+    let (remainder, _) = seeds(input)?;
+    Ok(Almanac::try_from(remainder)?
+        .analyse()
+        .iter()
+        .map(MapAnalysis::to_string)
+        .collect())
+}
+
+fn seed_ranges(seeds: &[u64]) -> Vec<Range<u64>> {
+    seeds
+        .chunks_exact(2)
+        .map(|pair| pair[0]..pair[0].saturating_add(pair[1]))
+        .collect()
+}
+
+fn lowest_location_forward(almanac: &Almanac, seed_ranges: &[Range<u64>]) -> Option<u64> {
+    seed_ranges
+        .iter()
+        .filter_map(|seeds| {
+            seeds
+                .clone()
+                .into_par_iter()
+                .map(|seed| almanac.get_location(seed))
+                .min()
+        })
+        .min()
+}
+
+fn lowest_location_inverse(almanac: &Almanac, seed_ranges: &[Range<u64>]) -> Option<u64> {
+    if seed_ranges.iter().all(Range::is_empty) {
+        return None;
+    }
+    (0..u64::MAX).find(|location| {
+        almanac
+            .seeds_for_locations(*location..location + 1)
+            .iter()
+            .any(|seeds| {
+                seed_ranges
+                    .iter()
+                    .any(|range| range.start < seeds.end && seeds.start < range.end)
+            })
+    })
+}
+
+/// The category maps in order, each mapping into the next, from `seed` to
+/// `location`.
+pub struct Almanac {
     maps: Vec<CategoryMap>,
 }
 
 impl Almanac {
+    fn new(maps: Vec<CategoryMap>) -> Result<Self> {
+        let mut category = "seed";
+        for map in &maps {
+            if map.source != category {
+                return Err(format!(
+                    "{}-to-{} map follows a map to {category}",
+                    map.source, map.destination
+                )
+                .into());
+            }
+            category = &map.destination;
+        }
+        if category != "location" {
+            return Err(format!("almanac ends at {category} instead of location").into());
+        }
+        Ok(Self { maps })
+    }
+
     fn get_location(&self, seed: u64) -> u64 {
         self.maps.iter().fold(seed, |key, map| map.get(key))
     }
+
+    /// Converts `value` from category `from` to the later category `to`.
+    /// `None` if either category is unknown or `to` comes before `from`.
+    pub fn convert(&self, from: &str, to: &str, value: u64) -> Option<u64> {
+        if from == to {
+            return self
+                .categories()
+                .any(|category| category == from)
+                .then_some(value);
+        }
+        let start = self.maps.iter().position(|map| map.source == from)?;
+        let end = start
+            + self.maps[start..]
+                .iter()
+                .position(|map| map.destination == to)?;
+        Some(
+            self.maps[start..=end]
+                .iter()
+                .fold(value, |key, map| map.get(key)),
+        )
+    }
+
+    /// A single map from seed to location equivalent to applying every
+    /// category map in turn.
+    fn composed(&self) -> PiecewiseMap {
+        self.maps
+            .iter()
+            .map(PiecewiseMap::from)
+            .reduce(|composed, next| composed.then(&next))
+            .unwrap_or_else(PiecewiseMap::identity)
+    }
+
+    /// The seed ranges that end up somewhere in `locations`.
+    pub fn seeds_for_locations(&self, locations: Range<u64>) -> Vec<Range<u64>> {
+        self.maps.iter().rev().fold(vec![locations], |ranges, map| {
+            merge(
+                ranges
+                    .into_iter()
+                    .flat_map(|range| map.inverse(range))
+                    .collect(),
+            )
+        })
+    }
+
+    pub fn analyse(&self) -> Vec<MapAnalysis> {
+        self.maps.iter().map(CategoryMap::analyse).collect()
+    }
+
+    /// One message per pair of rows that claim the same sources, since only
+    /// the first of them is ever used.
+    pub fn ambiguities(&self) -> Vec<String> {
+        self.analyse()
+            .iter()
+            .flat_map(|analysis| {
+                analysis.overlaps.iter().map(|overlap| {
+                    format!(
+                        "{}-to-{} map: {overlap}",
+                        analysis.source, analysis.destination
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = &str> {
+        self.maps
+            .first()
+            .map(|map| map.source.as_str())
+            .into_iter()
+            .chain(self.maps.iter().map(|map| map.destination.as_str()))
+    }
 }
 
-impl From<&str> for Almanac {
-    fn from(input: &str) -> Self {
-        let (_, almanac) = almanac_data(input).finish().unwrap();
-        Self {
-            maps: almanac
+impl TryFrom<&str> for Almanac {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(input: &str) -> Result<Self> {
+        let (_, almanac) = all_consuming(terminated(almanac_data, opt(newlines)))(input)
+            .finish()
+            .map_err(|err| format!("invalid almanac: {err}"))?;
+        Self::new(
+            almanac
                 .maps
                 .into_iter()
-                .map(|map_rows| CategoryMap { map_rows })
+                .map(|(source, destination, map_rows)| {
+                    CategoryMap::new(source, destination, map_rows)
+                })
                 .collect(),
-        }
+        )
     }
 }
 
 struct CategoryMap {
+    source: String,
+    destination: String,
     map_rows: MapRows,
 }
 
 impl CategoryMap {
+    fn new(source: &str, destination: &str, map_rows: MapRows) -> Self {
+        Self {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            map_rows,
+        }
+    }
+
     fn get(&self, source: u64) -> u64 {
         self.map_rows
             .iter()
             .find_map(|map_row| map_row.get_destination(source))
             .unwrap_or(source)
     }
+
+    /// Every source range that `get` maps into `destination`, sorted and
+    /// merged. A `Range<u64>` can't hold `u64::MAX`, so a row that maps
+    /// `u64::MAX` into `destination` only contributes the sources below it.
+    fn inverse(&self, destination: Range<u64>) -> Vec<Range<u64>> {
+        let mut sources = vec![];
+        // Sources claimed by earlier rows, which shadow later ones.
+        let mut claimed: Vec<Range<u64>> = vec![];
+        for map_row in &self.map_rows {
+            let row_sources = map_row.sources();
+            let dest_start = u128::from(map_row.dest_range_start);
+            let start = u128::from(destination.start).max(dest_start);
+            let end =
+                u128::from(destination.end).min(dest_start + (row_sources.end - row_sources.start));
+            if start < end {
+                let offset = start - dest_start;
+                let preimage =
+                    row_sources.start + offset..row_sources.start + offset + (end - start);
+                sources.extend(subtract(to_u64_range(preimage), &claimed));
+            }
+            claimed.push(map_row.source_range());
+        }
+        // Sources no row claims map to themselves.
+        sources.extend(subtract(destination, &claimed));
+        merge(sources)
+    }
+
+    /// Sorts the rows by source and sweeps over them to find where they
+    /// overlap, where they leave gaps and which sources map to themselves.
+    fn analyse(&self) -> MapAnalysis {
+        let mut rows = self
+            .map_rows
+            .iter()
+            .enumerate()
+            .map(|(index, map_row)| (index + 1, map_row.source_range()))
+            .filter(|(_, source_range)| !source_range.is_empty())
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|(row, source_range)| (source_range.start, *row));
+
+        let mut overlaps = vec![];
+        for (i, (first, first_range)) in rows.iter().enumerate() {
+            for (second, second_range) in rows[i + 1..]
+                .iter()
+                .take_while(|(_, range)| range.start < first_range.end)
+            {
+                overlaps.push(Overlap {
+                    rows: (*first.min(second), *first.max(second)),
+                    sources: second_range.start..first_range.end.min(second_range.end),
+                });
+            }
+        }
+        overlaps.sort_by_key(|overlap| overlap.rows);
+
+        let covered = merge(rows.iter().map(|(_, range)| range.clone()).collect());
+        let gaps = covered
+            .windows(2)
+            .map(|pair| pair[0].end..pair[1].start)
+            .collect();
+
+        let unmapped = subtract(0..u64::MAX, &covered);
+        let identity_rows = self
+            .map_rows
+            .iter()
+            .filter(|map_row| map_row.dest_range_start == map_row.source_range_start)
+            .map(MapRow::source_range);
+        let identity = merge(unmapped.into_iter().chain(identity_rows).collect());
+
+        MapAnalysis {
+            source: self.source.clone(),
+            destination: self.destination.clone(),
+            overlaps,
+            gaps,
+            identity,
+        }
+    }
+}
+
+/// What `CategoryMap::analyse` found out about one category map.
+#[derive(PartialEq, Eq, Debug)]
+pub struct MapAnalysis {
+    pub source: String,
+    pub destination: String,
+    /// Pairs of rows that both claim some sources.
+    pub overlaps: Vec<Overlap>,
+    /// Sources between the lowest and highest rows that no row claims.
+    pub gaps: Vec<Range<u64>>,
+    /// Sources that map to themselves, whether or not a row claims them.
+    pub identity: Vec<Range<u64>>,
+}
+
+/// Two rows, numbered from 1 in the order they appear in their map, that
+/// both claim `sources`. The earlier row shadows the later one.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Overlap {
+    pub rows: (usize, usize),
+    pub sources: Range<u64>,
+}
+
+impl Display for Overlap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rows {} and {} both map {:?}",
+            self.rows.0, self.rows.1, self.sources
+        )
+    }
+}
+
+impl Display for MapAnalysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}-to-{} map:", self.source, self.destination)?;
+        for overlap in &self.overlaps {
+            writeln!(f, "  overlap: {overlap}")?;
+        }
+        for gap in &self.gaps {
+            writeln!(f, "  gap: {gap:?}")?;
+        }
+        for identity in &self.identity {
+            writeln!(f, "  identity: {identity:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A map that shifts each of a series of contiguous source ranges by a
+/// constant. The pieces are sorted and together cover every `u64`, up to
+/// and including `u64::MAX`, so their bounds are `u128`s ending at
+/// `U64_END`. Every destination fits in a `u64` too.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct PiecewiseMap {
+    pieces: Vec<Piece>,
+}
+
+/// One past `u64::MAX`.
+const U64_END: u128 = 1 << 64;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Piece {
+    source: Range<u128>,
+    dest_start: u128,
+}
+
+impl Piece {
+    fn get(&self, source: u128) -> u128 {
+        self.dest_start + (source - self.source.start)
+    }
+
+    fn dest_end(&self) -> u128 {
+        self.get(self.source.end)
+    }
+}
+
+impl PiecewiseMap {
+    fn identity() -> Self {
+        Self::new(vec![Piece {
+            source: 0..U64_END,
+            dest_start: 0,
+        }])
+    }
+
+    /// Joins neighbouring pieces that shift by the same amount.
+    fn new(pieces: Vec<Piece>) -> Self {
+        let pieces = pieces
+            .into_iter()
+            .fold(vec![], |mut pieces: Vec<Piece>, piece| {
+                match pieces.last_mut() {
+                    Some(last) if last.dest_end() == piece.dest_start => {
+                        last.source.end = piece.source.end
+                    }
+                    _ => pieces.push(piece),
+                }
+                pieces
+            });
+        Self { pieces }
+    }
+
+    fn piece_at(&self, source: u128) -> Option<&Piece> {
+        let index = self
+            .pieces
+            .partition_point(|piece| piece.source.end <= source);
+        self.pieces.get(index)
+    }
+
+    fn get(&self, source: u64) -> u64 {
+        let piece = self.piece_at(source.into()).unwrap();
+        piece.get(source.into()) as u64
+    }
+
+    /// The smallest value any source in `sources` maps to.
+    fn min_over(&self, sources: Range<u64>) -> Option<u64> {
+        if sources.is_empty() {
+            return None;
+        }
+        let (start, end) = (u128::from(sources.start), u128::from(sources.end));
+        let first = self
+            .pieces
+            .partition_point(|piece| piece.source.end <= start);
+        self.pieces[first..]
+            .iter()
+            .take_while(|piece| piece.source.start < end)
+            .map(|piece| piece.get(piece.source.start.max(start)) as u64)
+            .min()
+    }
+
+    /// The map that applies `self` and then `next`.
+    fn then(&self, next: &PiecewiseMap) -> PiecewiseMap {
+        let mut pieces = vec![];
+        for piece in &self.pieces {
+            let mut start = piece.source.start;
+            while start < piece.source.end {
+                let dest = piece.get(start);
+                let next_piece = next.piece_at(dest).unwrap();
+                let length = (piece.source.end - start).min(next_piece.source.end - dest);
+                pieces.push(Piece {
+                    source: start..start + length,
+                    dest_start: next_piece.get(dest),
+                });
+                start += length;
+            }
+        }
+        PiecewiseMap::new(pieces)
+    }
+}
+
+impl From<&CategoryMap> for PiecewiseMap {
+    fn from(category_map: &CategoryMap) -> Self {
+        let mut bounds = vec![0, U64_END];
+        for map_row in &category_map.map_rows {
+            let sources = map_row.sources();
+            bounds.extend([sources.start, sources.end]);
+        }
+        bounds.sort();
+        bounds.dedup();
+        // Every row starts and ends on a bound, so `get` shifts each span
+        // between bounds by a single amount.
+        PiecewiseMap::new(
+            bounds
+                .windows(2)
+                .map(|bounds| Piece {
+                    source: bounds[0]..bounds[1],
+                    dest_start: category_map.get(bounds[0] as u64).into(),
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Display for PiecewiseMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sources = self
+            .pieces
+            .iter()
+            .map(|piece| format!("{:?}", piece.source))
+            .collect::<Vec<_>>();
+        let width = sources.iter().map(String::len).max().unwrap_or(0);
+        for (piece, source) in self.pieces.iter().zip(sources) {
+            writeln!(
+                f,
+                "{source:>width$} -> {:?}",
+                piece.dest_start..piece.dest_end()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// `range` without any of it past `u64::MAX`.
+fn to_u64_range(range: Range<u128>) -> Range<u64> {
+    let end = range.end.min(u64::MAX.into());
+    range.start.min(end) as u64..end as u64
+}
+
+/// The parts of `range` not covered by any of `holes`.
+fn subtract(range: Range<u64>, holes: &[Range<u64>]) -> Vec<Range<u64>> {
+    holes.iter().fold(vec![range], |pieces, hole| {
+        pieces
+            .into_iter()
+            .flat_map(|piece| {
+                [
+                    piece.start..piece.end.min(hole.start),
+                    piece.start.max(hole.end)..piece.end,
+                ]
+            })
+            .filter(|piece| !piece.is_empty())
+            .collect()
+    })
+}
+
+/// Sorts `ranges` and joins any that overlap or touch.
+fn merge(mut ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    ranges.sort_by_key(|range| range.start);
+    ranges
+        .into_iter()
+        .fold(vec![], |mut merged: Vec<Range<u64>>, range| {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+            merged
+        })
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -84,11 +585,28 @@ struct MapRow {
 }
 
 impl MapRow {
+    /// Exactly the sources `get_destination` maps: those whose destination
+    /// fits in `u64` as well. The end can be `U64_END`, hence `u128`.
+    fn sources(&self) -> Range<u128> {
+        let start = u128::from(self.source_range_start);
+        let length = u128::from(self.range_length)
+            .min(U64_END - start)
+            .min(U64_END - u128::from(self.dest_range_start));
+        start..start + length
+    }
+
+    /// `sources` short of `u64::MAX`, which a `Range<u64>` can't hold.
+    fn source_range(&self) -> Range<u64> {
+        to_u64_range(self.sources())
+    }
+
+    /// Maps `source` if it lies in the half-open range
+    /// `source_range_start..source_range_start + range_length`. Rows whose
+    /// destination would overflow `u64` map nothing.
     pub fn get_destination(&self, source: u64) -> Option<u64> {
-        if self.source_range_start <= source
-            && source <= self.source_range_start + self.range_length
-        {
-            Some(source + self.dest_range_start - self.source_range_start)
+        let offset = source.checked_sub(self.source_range_start)?;
+        if offset < self.range_length {
+            self.dest_range_start.checked_add(offset)
         } else {
             None
         }
@@ -97,10 +615,13 @@ impl MapRow {
 
 type MapRows = Vec<MapRow>;
 
-struct AlmanacData {
-    maps: Vec<MapRows>,
+struct AlmanacData<'a> {
+    maps: Vec<Mapping<'a>>,
 }
 
+/// Source category, destination category and rows of one map.
+type Mapping<'a> = (&'a str, &'a str, MapRows);
+
 fn almanac_data(input: &str) -> IResult<&str, AlmanacData> {
     map(mappings, |maps| AlmanacData { maps })(input)
 }
@@ -109,35 +630,30 @@ fn seeds(input: &str) -> IResult<&str, Vec<u64>> {
     terminated(preceded(tag("seeds: "), numbers), newlines)(input)
 }
 
-fn mappings(input: &str) -> IResult<&str, Vec<MapRows>> {
+fn mappings(input: &str) -> IResult<&str, Vec<Mapping>> {
     separated_list1(newlines, mapping)(input)
 }
 
-fn mapping(input: &str) -> IResult<&str, MapRows> {
-    preceded(map_name, separated_list1(newline, map_row))(input)
+fn mapping(input: &str) -> IResult<&str, Mapping> {
+    map(
+        tuple((map_name, separated_list1(newline, map_row))),
+        |((source, destination), map_rows)| (source, destination, map_rows),
+    )(input)
 }
 
-fn map_name(input: &str) -> IResult<&str, &str> {
-    alt((
-        tag("seed-to-soil map:\n"),
-        tag("soil-to-fertilizer map:\n"),
-        tag("fertilizer-to-water map:\n"),
-        tag("water-to-light map:\n"),
-        tag("light-to-temperature map:\n"),
-        tag("temperature-to-humidity map:\n"),
-        tag("humidity-to-location map:\n"),
-    ))(input)
+fn map_name(input: &str) -> IResult<&str, (&str, &str)> {
+    terminated(separated_pair(alpha1, tag("-to-"), alpha1), tag(" map:\n"))(input)
 }
 
 fn map_row(input: &str) -> IResult<&str, MapRow> {
-    map(numbers, |numbers| {
-        let mut it = numbers.into_iter();
-        MapRow {
-            dest_range_start: it.next().unwrap(),
-            source_range_start: it.next().unwrap(),
-            range_length: it.next().unwrap(),
-        }
-    })(input)
+    map(
+        tuple((u64, char(' '), u64, char(' '), u64)),
+        |(dest_range_start, _, source_range_start, _, range_length)| MapRow {
+            dest_range_start,
+            source_range_start,
+            range_length,
+        },
+    )(input)
 }
 
 fn numbers(input: &str) -> IResult<&str, Vec<u64>> {
@@ -165,6 +681,295 @@ mod tests {
         Ok(())
     }
 
+    const EXAMPLE: &str = "seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4";
+
+    #[test]
+    fn test_almanac_convert() -> Result<()> {
+        let (remainder, seeds) = seeds(EXAMPLE)?;
+        let almanac = Almanac::try_from(remainder)?;
+        assert_eq!(seeds, vec![79, 14, 55, 13]);
+
+        let locations = seeds
+            .iter()
+            .map(|seed| almanac.get_location(*seed))
+            .collect::<Vec<_>>();
+        assert_eq!(locations, vec![82, 43, 86, 35]);
+
+        // Seed 79 is soil 81, fertilizer 81, water 81, light 74,
+        // temperature 78, humidity 78 and location 82.
+        assert_eq!(almanac.convert("seed", "location", 79), Some(82));
+        assert_eq!(almanac.convert("seed", "soil", 79), Some(81));
+        assert_eq!(almanac.convert("soil", "humidity", 81), Some(78));
+        assert_eq!(almanac.convert("light", "light", 74), Some(74));
+        assert_eq!(almanac.convert("humidity", "soil", 78), None);
+        assert_eq!(almanac.convert("seed", "planet", 79), None);
+        assert_eq!(almanac.convert("planet", "planet", 79), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_part_2_strategies() -> Result<()> {
+        let (remainder, seeds) = seeds(EXAMPLE)?;
+        let almanac = Almanac::try_from(remainder)?;
+        let seed_ranges = seed_ranges(&seeds);
+        assert_eq!(seed_ranges, vec![79..93, 55..68]);
+
+        assert_eq!(lowest_location_forward(&almanac, &seed_ranges), Some(46));
+        assert_eq!(lowest_location_inverse(&almanac, &seed_ranges), Some(46));
+        assert_eq!(lowest_location_inverse(&almanac, &[]), None);
+
+        let seeds = almanac.seeds_for_locations(46..47);
+        assert!(seeds.iter().any(|range| range.contains(&82)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_composed() -> Result<()> {
+        let (remainder, seeds) = seeds(EXAMPLE)?;
+        let almanac = Almanac::try_from(remainder)?;
+        let composed = almanac.composed();
+
+        for seed in seeds {
+            assert_eq!(composed.get(seed), almanac.get_location(seed));
+        }
+        assert_eq!(composed.min_over(79..93), Some(46));
+        assert_eq!(composed.min_over(55..68), Some(56));
+        assert_eq!(composed.min_over(55..55), None);
+        assert_eq!(composed.get(u64::MAX), u64::MAX);
+
+        let table = composed.to_string();
+        assert_eq!(table.lines().count(), composed.pieces.len());
+        let width = format!("100..{U64_END}").len();
+        assert!(table.starts_with(&format!("{:>width$} -> 22..36\n", "0..14")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_piecewise_map_from_category_map() {
+        let category_map = CategoryMap::new(
+            "seed",
+            "soil",
+            vec![
+                MapRow {
+                    dest_range_start: 50,
+                    source_range_start: 98,
+                    range_length: 2,
+                },
+                MapRow {
+                    dest_range_start: 52,
+                    source_range_start: 50,
+                    range_length: 48,
+                },
+            ],
+        );
+        assert_eq!(
+            PiecewiseMap::from(&category_map).pieces,
+            vec![
+                Piece {
+                    source: 0..50,
+                    dest_start: 0,
+                },
+                Piece {
+                    source: 50..98,
+                    dest_start: 52,
+                },
+                Piece {
+                    source: 98..100,
+                    dest_start: 50,
+                },
+                Piece {
+                    source: 100..U64_END,
+                    dest_start: 100,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subtract_and_merge() {
+        assert_eq!(subtract(0..10, &[2..4, 6..8]), vec![0..2, 4..6, 8..10]);
+        assert_eq!(subtract(3..5, &[0..4, 4..10]), vec![]);
+        assert_eq!(subtract(3..5, &[]), vec![3..5]);
+        assert_eq!(merge(vec![8..10, 0..2, 2..4, 3..5]), vec![0..5, 8..10]);
+    }
+
+    #[test]
+    fn test_category_map_inverse() {
+        let category_map = CategoryMap::new(
+            "seed",
+            "soil",
+            vec![
+                MapRow {
+                    dest_range_start: 50,
+                    source_range_start: 98,
+                    range_length: 2,
+                },
+                MapRow {
+                    dest_range_start: 52,
+                    source_range_start: 50,
+                    range_length: 48,
+                },
+            ],
+        );
+        assert_eq!(category_map.inverse(50..52), vec![98..100]);
+        assert_eq!(category_map.inverse(0..10), vec![0..10]);
+        assert_eq!(category_map.inverse(51..53), vec![50..51, 99..100]);
+    }
+
+    #[test]
+    fn test_category_map_analyse() {
+        let category_map = CategoryMap::new(
+            "seed",
+            "soil",
+            vec![
+                MapRow {
+                    dest_range_start: 50,
+                    source_range_start: 98,
+                    range_length: 2,
+                },
+                MapRow {
+                    dest_range_start: 52,
+                    source_range_start: 50,
+                    range_length: 48,
+                },
+                MapRow {
+                    dest_range_start: 10,
+                    source_range_start: 10,
+                    range_length: 5,
+                },
+                MapRow {
+                    dest_range_start: 0,
+                    source_range_start: 20,
+                    range_length: 5,
+                },
+            ],
+        );
+        assert_eq!(
+            category_map.analyse(),
+            MapAnalysis {
+                source: "seed".to_string(),
+                destination: "soil".to_string(),
+                overlaps: vec![],
+                gaps: vec![15..20, 25..50],
+                identity: vec![0..20, 25..50, 100..u64::MAX],
+            }
+        );
+
+        let category_map = CategoryMap::new(
+            "seed",
+            "soil",
+            vec![
+                MapRow {
+                    dest_range_start: 0,
+                    source_range_start: 20,
+                    range_length: 10,
+                },
+                MapRow {
+                    dest_range_start: 100,
+                    source_range_start: 10,
+                    range_length: 30,
+                },
+                MapRow {
+                    dest_range_start: 200,
+                    source_range_start: 25,
+                    range_length: 2,
+                },
+            ],
+        );
+        assert_eq!(
+            category_map.analyse().overlaps,
+            vec![
+                Overlap {
+                    rows: (1, 2),
+                    sources: 20..30,
+                },
+                Overlap {
+                    rows: (1, 3),
+                    sources: 25..27,
+                },
+                Overlap {
+                    rows: (2, 3),
+                    sources: 25..27,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_almanac_ambiguities() -> Result<()> {
+        let (remainder, _) = seeds(EXAMPLE)?;
+        assert!(Almanac::try_from(remainder)?.ambiguities().is_empty());
+
+        let overlapping = remainder.replace("52 50 48", "52 50 49");
+        assert_eq!(
+            Almanac::try_from(overlapping.as_str())?.ambiguities(),
+            vec!["seed-to-soil map: rows 1 and 2 both map 98..99"]
+        );
+        assert!(Strictness::Deny
+            .check(&Almanac::try_from(overlapping.as_str())?.ambiguities())
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_almanac_chain() {
+        let broken = EXAMPLE
+            .split_once("\n\n")
+            .unwrap()
+            .1
+            .replace("water-to-light", "air-to-light");
+        let err = Almanac::try_from(broken.as_str()).err().unwrap();
+        assert_eq!(err.to_string(), "air-to-light map follows a map to water");
+
+        let truncated = EXAMPLE.split_once("\n\n").unwrap().1;
+        let truncated = &truncated[..truncated.find("\n\nhumidity").unwrap()];
+        let err = Almanac::try_from(truncated).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "almanac ends at humidity instead of location"
+        );
+    }
+
+    #[test]
+    fn test_map_name() -> Result<()> {
+        assert_eq!(map_name("seed-to-soil map:\n")?.1, ("seed", "soil"));
+        assert_eq!(map_name("air-to-water map:\n")?.1, ("air", "water"));
+        assert!(map_name("seed-soil map:\n").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_category_map_get() {
         let map_rows = vec![
@@ -184,12 +989,13 @@ mod tests {
                 range_length: 15,
             },
         ];
-        let category_map = CategoryMap { map_rows };
+        let category_map = CategoryMap::new("soil", "fertilizer", map_rows);
         assert_eq!(category_map.get(0), 39);
         assert_eq!(category_map.get(14), 53);
         assert_eq!(category_map.get(15), 0);
         assert_eq!(category_map.get(52), 37);
-        assert_eq!(category_map.get(54), 39);
+        assert_eq!(category_map.get(53), 38);
+        assert_eq!(category_map.get(54), 54);
         assert_eq!(category_map.get(55), 55);
     }
 
@@ -197,9 +1003,10 @@ mod tests {
     fn test_mapping() {
         let input = ["soil-to-fertilizer map:", "0 15 37", "37 52 2", "39 0 15"].join("\n");
 
-        let (_, map_row) = mapping(&input).unwrap();
+        let (_, (source, destination, map_rows)) = mapping(&input).unwrap();
+        assert_eq!((source, destination), ("soil", "fertilizer"));
         assert_eq!(
-            map_row,
+            map_rows,
             vec![
                 MapRow {
                     dest_range_start: 0,
@@ -231,21 +1038,236 @@ mod tests {
         assert_eq!(map_row.get_destination(15), Some(0));
         assert_eq!(map_row.get_destination(16), Some(1));
         assert_eq!(map_row.get_destination(51), Some(36));
-        assert_eq!(map_row.get_destination(52), Some(37));
+        assert_eq!(map_row.get_destination(52), None);
         assert_eq!(map_row.get_destination(53), None);
     }
 
+    #[test]
+    fn test_map_row_get_destination_overflow() {
+        let map_row = MapRow {
+            dest_range_start: u64::MAX - 1,
+            source_range_start: 10,
+            range_length: 5,
+        };
+        assert_eq!(map_row.get_destination(0), None);
+        assert_eq!(map_row.get_destination(11), Some(u64::MAX));
+        assert_eq!(map_row.get_destination(12), None);
+
+        let map_row = MapRow {
+            dest_range_start: 0,
+            source_range_start: u64::MAX - 1,
+            range_length: 5,
+        };
+        assert_eq!(map_row.get_destination(u64::MAX), Some(1));
+    }
+
+    #[test]
+    fn test_composed_near_u64_max() -> Result<()> {
+        let almanac = Almanac::new(vec![
+            CategoryMap::new(
+                "seed",
+                "soil",
+                vec![MapRow {
+                    dest_range_start: u64::MAX - 1,
+                    source_range_start: 10,
+                    range_length: 5,
+                }],
+            ),
+            CategoryMap::new(
+                "soil",
+                "location",
+                vec![MapRow {
+                    dest_range_start: 0,
+                    source_range_start: u64::MAX - 1,
+                    range_length: 5,
+                }],
+            ),
+        ])?;
+        let composed = almanac.composed();
+        for seed in [9, 10, 11, 12, u64::MAX - 1, u64::MAX] {
+            assert_eq!(
+                composed.get(seed),
+                almanac.get_location(seed),
+                "seed {seed}"
+            );
+        }
+        // 11 reaches u64::MAX halfway, and u64::MAX itself maps to 1.
+        assert_eq!(composed.get(11), 1);
+        assert_eq!(composed.get(12), 12);
+        assert_eq!(composed.get(u64::MAX), 1);
+        assert_eq!(composed.min_over(11..13), Some(1));
+        // Seed 11 no longer lands on location 11, so only 12 does.
+        assert_eq!(almanac.seeds_for_locations(11..13), vec![12..13]);
+        Ok(())
+    }
+
     #[test]
     fn test_map_row() {
-        let input = "50 98 2";
-        let (_, map_row) = map_row(input).unwrap();
         assert_eq!(
-            map_row,
-            MapRow {
-                dest_range_start: 50,
-                source_range_start: 98,
-                range_length: 2
-            }
+            map_row("50 98 2"),
+            Ok((
+                "",
+                MapRow {
+                    dest_range_start: 50,
+                    source_range_start: 98,
+                    range_length: 2
+                }
+            ))
         );
+        assert!(map_row("50 98").is_err());
+
+        let short = "seed-to-location map:\n50 98\n52 50 48";
+        assert!(Almanac::try_from(short).is_err());
+        let long = "seed-to-location map:\n50 98 2 7\n52 50 48\n";
+        assert!(Almanac::try_from(long).is_err());
+        assert!(Almanac::try_from("seed-to-location map:\n50 98 2\n52 50 48\n").is_ok());
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use std::collections::HashMap;
+
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn map_row() -> impl Strategy<Value = MapRow> {
+        (0_u64..200, 0_u64..200, 0_u64..50).prop_map(
+            |(dest_range_start, source_range_start, range_length)| MapRow {
+                dest_range_start,
+                source_range_start,
+                range_length,
+            },
+        )
+    }
+
+    // Rows whose ranges may run up against `u64::MAX`.
+    fn map_row_near_max() -> impl Strategy<Value = MapRow> {
+        let value = prop_oneof![0_u64..200, u64::MAX - 200..=u64::MAX];
+        (value.clone(), value, 0_u64..50).prop_map(
+            |(dest_range_start, source_range_start, range_length)| MapRow {
+                dest_range_start,
+                source_range_start,
+                range_length,
+            },
+        )
+    }
+
+    // Spells out every mapped value, letting earlier rows win like `get`.
+    fn reference_model(map_rows: &[MapRow]) -> HashMap<u64, u64> {
+        let mut model = HashMap::new();
+        for map_row in map_rows.iter().rev() {
+            for offset in 0..map_row.range_length {
+                model.insert(
+                    map_row.source_range_start + offset,
+                    map_row.dest_range_start + offset,
+                );
+            }
+        }
+        model
+    }
+
+    proptest! {
+        #[test]
+        fn category_map_get_matches_reference(
+            map_rows in prop::collection::vec(map_row(), 0..6),
+            source in 0_u64..300,
+        ) {
+            let model = reference_model(&map_rows);
+            let category_map = CategoryMap::new("soil", "fertilizer", map_rows);
+            prop_assert_eq!(
+                category_map.get(source),
+                model.get(&source).copied().unwrap_or(source)
+            );
+        }
+
+        #[test]
+        fn category_map_inverse_matches_get(
+            map_rows in prop::collection::vec(map_row(), 0..6),
+            destination in 0_u64..300,
+        ) {
+            let category_map = CategoryMap::new("a", "b", map_rows);
+            let sources = category_map.inverse(destination..destination + 1);
+            for source in 0..300 {
+                prop_assert_eq!(
+                    sources.iter().any(|range| range.contains(&source)),
+                    category_map.get(source) == destination,
+                    "source {}", source
+                );
+            }
+            prop_assert!(sources.iter().all(|range| range.end <= 300));
+        }
+
+        #[test]
+        fn composed_matches_chain(
+            maps in prop::collection::vec(prop::collection::vec(map_row(), 0..4), 0..4),
+            seeds in prop::collection::vec(0_u64..300, 1..10),
+            range in (0_u64..300, 0_u64..50),
+        ) {
+            let num_maps = maps.len();
+            let categories = (0..=num_maps)
+                .map(|index| match index {
+                    0 => "seed".to_string(),
+                    index if index == num_maps => "location".to_string(),
+                    index => format!("category{index}"),
+                })
+                .collect::<Vec<_>>();
+            let almanac = Almanac::new(
+                maps.into_iter()
+                    .enumerate()
+                    .map(|(index, map_rows)| {
+                        CategoryMap::new(&categories[index], &categories[index + 1], map_rows)
+                    })
+                    .collect(),
+            );
+            // With no maps the chain can't get from seed to location.
+            prop_assume!(almanac.is_ok());
+            let almanac = almanac.unwrap();
+            let composed = almanac.composed();
+
+            for seed in seeds {
+                prop_assert_eq!(composed.get(seed), almanac.get_location(seed));
+            }
+            let (start, length) = range;
+            prop_assert_eq!(
+                composed.min_over(start..start + length),
+                (start..start + length).map(|seed| almanac.get_location(seed)).min()
+            );
+        }
+
+        #[test]
+        fn composed_matches_chain_near_max(
+            soil_rows in prop::collection::vec(map_row_near_max(), 0..4),
+            location_rows in prop::collection::vec(map_row_near_max(), 0..4),
+            offset in 0_u64..250,
+        ) {
+            let almanac = Almanac::new(vec![
+                CategoryMap::new("seed", "soil", soil_rows),
+                CategoryMap::new("soil", "location", location_rows),
+            ])
+            .unwrap();
+            let composed = almanac.composed();
+            for seed in [offset, u64::MAX - offset] {
+                prop_assert_eq!(composed.get(seed), almanac.get_location(seed));
+            }
+            let top = u64::MAX - offset..u64::MAX;
+            prop_assert_eq!(
+                composed.min_over(top.clone()),
+                top.map(|seed| almanac.get_location(seed)).min()
+            );
+        }
+
+        #[test]
+        fn map_row_range_is_half_open(map_row in map_row()) {
+            let end = map_row.source_range_start + map_row.range_length;
+            prop_assert_eq!(map_row.get_destination(end), None);
+            if map_row.range_length > 0 {
+                prop_assert_eq!(
+                    map_row.get_destination(end - 1),
+                    Some(map_row.dest_range_start + map_row.range_length - 1)
+                );
+            }
+        }
     }
 }
@@ -1,30 +1,33 @@
-use itertools::Itertools;
 use nom::{
     bytes::complete::tag,
     character::complete::{digit1, newline, space1},
-    combinator::map,
     multi::many1,
     sequence::{preceded, terminated, tuple},
     Finish, IResult,
 };
 
+use std::ops::RangeInclusive;
+
 use crate::{Result, Solution};
 
 pub fn solution() -> Result<Solution<u64>> {
+    solution_with_acceleration(1)
+}
+
+/// Solves for boats that gain `acceleration` mm/ms of speed for every ms the
+/// button is held.
+pub fn solution_with_acceleration(acceleration: u64) -> Result<Solution<u64>> {
     let input = include_str!("../input/6.txt");
 
-    let part_1 = parse_records(input)
+    let races = Races::try_from(input)?;
+
+    let part_1 = races
+        .separate()?
         .iter()
-        .map(|record| {
-            let (min_time_held, max_time_held) = roots(record.time, record.distance);
-            max_time_held - min_time_held + 1
-        })
+        .map(|record| record.ways_to_win(acceleration))
         .product();
 
-    let record = parse_record(input);
-
-    let part_2 = roots(record.time, record.distance);
-    let part_2 = part_2.1 - part_2.0 + 1;
+    let part_2 = races.kerned()?.ways_to_win(acceleration);
 
     Ok(Solution {
         day: 6,
@@ -33,49 +36,143 @@ pub fn solution() -> Result<Solution<u64>> {
     })
 }
 
-fn roots(time: u64, distance: u64) -> (u64, u64) {
-    // t^2 - tT + D = 0
-    // Complete the square
-    let time_over_2 = time as f64 / 2_f64;
-    let distance = distance as f64;
-    let root = f64::sqrt(f64::powi(time_over_2, 2) - distance);
-    (
-        f64::ceil(-root + time_over_2) as u64,
-        f64::floor(root + time_over_2) as u64,
-    )
+/// A table of every race's winning hold times and the hold that goes
+/// furthest.
+pub fn explain(acceleration: u64) -> Result<String> {
+    let input = include_str!("../input/6.txt");
+    Ok(breakdown_table(
+        &Races::try_from(input)?.separate()?,
+        acceleration,
+    ))
 }
 
-#[derive(Debug)]
-struct Record {
-    time: u64,
-    distance: u64,
+fn breakdown_table(records: &[Record], acceleration: u64) -> String {
+    let mut table = format!(
+        "{:>4} | {:>6} | {:>8} | {:>13} | {:>6} | {:>9} | Best distance\n",
+        "Race", "Time", "Record", "Winning holds", "Ways", "Best hold"
+    );
+    for (index, record) in records.iter().enumerate() {
+        let winning_holds = record
+            .winning_holds(acceleration)
+            .map_or("none".to_string(), |holds| format!("{holds:?}"));
+        let optimal_hold = record.optimal_hold();
+        table += &format!(
+            "{:>4} | {:>6} | {:>8} | {:>13} | {:>6} | {:>9} | {}\n",
+            index + 1,
+            record.time,
+            record.distance,
+            winning_holds,
+            record.ways_to_win(acceleration),
+            optimal_hold,
+            record.distance_travelled(optimal_hold, acceleration)
+        );
+    }
+    table
 }
 
-fn parse_record(input: &str) -> Record {
-    let (_, record) = map(times_and_distances, |(times, distances)| {
-        let time = times.join("").parse().unwrap();
-        let distance = distances.join("").parse().unwrap();
-        Record { time, distance }
-    })(input)
-    .finish()
-    .unwrap();
-    record
+/// A race lasting `time` ms whose best result so far is `distance` mm.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Record {
+    pub time: u64,
+    pub distance: u64,
 }
 
-fn parse_records(input: &str) -> Vec<Record> {
-    let (_, records) = map(times_and_distances, |(times, distances)| {
-        times
-            .into_iter()
-            .zip(distances)
-            .map(|(time, distance)| Record {
-                time: time.parse().unwrap(),
-                distance: distance.parse().unwrap(),
+impl Record {
+    /// How far a boat goes when the button is held for `hold` ms: it then
+    /// moves for the rest of the race at `acceleration * hold` mm/ms.
+    pub fn distance_travelled(&self, hold: u64, acceleration: u64) -> u128 {
+        let hold = hold.min(self.time);
+        (acceleration as u128)
+            .saturating_mul(hold as u128)
+            .saturating_mul((self.time - hold) as u128)
+    }
+
+    /// The hold time that goes furthest, the lower of the two when `time` is
+    /// odd.
+    pub fn optimal_hold(&self) -> u64 {
+        self.time / 2
+    }
+
+    /// The hold times that beat the record, or `None` if none does. The
+    /// distance grows up to `optimal_hold` and is symmetric about `time / 2`,
+    /// so the shortest winning hold is found by bisecting below it.
+    pub fn winning_holds(&self, acceleration: u64) -> Option<RangeInclusive<u64>> {
+        let beats_record =
+            |hold| self.distance_travelled(hold, acceleration) > self.distance as u128;
+        let (mut low, mut high) = (0, self.optimal_hold());
+        if !beats_record(high) {
+            return None;
+        }
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if beats_record(mid) {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        Some(low..=self.time - low)
+    }
+
+    pub fn ways_to_win(&self, acceleration: u64) -> u64 {
+        self.winning_holds(acceleration)
+            .map_or(0, |holds| holds.end() - holds.start() + 1)
+    }
+}
+
+/// The time and distance rows of the sheet, with each number kept as written
+/// so that the rows can be read either as separate races or, ignoring the
+/// spaces between numbers, as one long race.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Races<'a> {
+    times: Vec<&'a str>,
+    distances: Vec<&'a str>,
+}
+
+impl<'a> Races<'a> {
+    /// One race per column.
+    pub fn separate(&self) -> Result<Vec<Record>> {
+        self.times
+            .iter()
+            .zip(&self.distances)
+            .map(|(time, distance)| {
+                Ok(Record {
+                    time: parse_number(time)?,
+                    distance: parse_number(distance)?,
+                })
             })
-            .collect_vec()
-    })(input)
-    .finish()
-    .unwrap();
-    records
+            .collect()
+    }
+
+    /// A single race whose time and distance are each row's digits run
+    /// together.
+    pub fn kerned(&self) -> Result<Record> {
+        Ok(Record {
+            time: parse_number(&self.times.concat())?,
+            distance: parse_number(&self.distances.concat())?,
+        })
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Races<'a> {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(input: &'a str) -> Result<Self> {
+        let (_, (times, distances)) = times_and_distances(input)
+            .finish()
+            .map_err(|err| format!("invalid races: {err}"))?;
+        if times.len() != distances.len() {
+            return Err(format!("{} times but {} distances", times.len(), distances.len()).into());
+        }
+        Ok(Self { times, distances })
+    }
+}
+
+/// Parses a run of digits, which only fails if it overflows `u64`.
+fn parse_number(digits: &str) -> Result<u64> {
+    digits
+        .parse()
+        .map_err(|_| format!("{digits} overflows u64").into())
 }
 
 fn times_and_distances(input: &str) -> IResult<&str, (Vec<&str>, Vec<&str>)> {
@@ -111,12 +208,104 @@ mod tests {
     }
 
     #[test]
-    fn test_roots() {
-        assert_eq!(roots(7, 9), (2, 5))
+    fn test_winning_holds() {
+        let record = |time, distance| Record { time, distance };
+        assert_eq!(record(7, 9).winning_holds(1), Some(2..=5));
+        assert_eq!(record(15, 40).winning_holds(1), Some(4..=11));
+        // Holding for 10 or 20 ms only ties the record.
+        assert_eq!(record(30, 200).winning_holds(1), Some(11..=19));
+        assert_eq!(record(4, 4).winning_holds(1), None);
+        assert_eq!(record(7, 9).winning_holds(2), Some(1..=6));
+        assert_eq!(record(7, 9).winning_holds(0), None);
+    }
+
+    #[test]
+    fn test_optimal_hold() {
+        let record = Record {
+            time: 7,
+            distance: 9,
+        };
+        assert_eq!(record.optimal_hold(), 3);
+        assert_eq!(record.distance_travelled(3, 1), 12);
+        assert_eq!(record.distance_travelled(4, 1), 12);
+        assert_eq!(record.distance_travelled(3, 3), 36);
+        assert_eq!(record.ways_to_win(1), 4);
     }
 
+    #[test]
+    fn test_breakdown_table() {
+        let records = [
+            Record {
+                time: 7,
+                distance: 9,
+            },
+            Record {
+                time: 4,
+                distance: 4,
+            },
+        ];
+        assert_eq!(
+            breakdown_table(&records, 1),
+            "\
+Race |   Time |   Record | Winning holds |   Ways | Best hold | Best distance
+   1 |      7 |        9 |         2..=5 |      4 |         3 | 12
+   2 |      4 |        4 |          none |      0 |         2 | 4
+"
+        );
+    }
+
+    const EXAMPLE: &str = "Time:      7  15   30
+Distance:  9  40  200";
+
     #[test]
     fn test_times_and_distances() {
-        assert_eq!()
+        assert_eq!(
+            times_and_distances(EXAMPLE),
+            Ok(("", (vec!["7", "15", "30"], vec!["9", "40", "200"])))
+        );
+    }
+
+    #[test]
+    fn test_races() -> Result<()> {
+        let races = Races::try_from(EXAMPLE)?;
+        assert_eq!(
+            races.separate()?,
+            vec![
+                Record {
+                    time: 7,
+                    distance: 9
+                },
+                Record {
+                    time: 15,
+                    distance: 40
+                },
+                Record {
+                    time: 30,
+                    distance: 200
+                },
+            ]
+        );
+        assert_eq!(
+            races.kerned()?,
+            Record {
+                time: 71530,
+                distance: 940200
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_races_errors() -> Result<()> {
+        let err = Races::try_from("Time: 7 15\nDistance: 9").err().unwrap();
+        assert_eq!(err.to_string(), "2 times but 1 distances");
+
+        let races = Races::try_from("Time: 4294967296 4294967296\nDistance: 1 2")?;
+        assert_eq!(races.separate()?.len(), 2);
+        assert_eq!(
+            races.kerned().err().unwrap().to_string(),
+            "42949672964294967296 overflows u64"
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,205 @@
+use nom::{
+    character::complete::{one_of, space1, u64},
+    combinator::{all_consuming, map_opt},
+    multi::count,
+    sequence::separated_pair,
+    Finish, IResult,
+};
+
+use crate::{Result, Solution};
+
+pub fn solution() -> Result<Solution<u64>> {
+    let input = include_str!("../input/7.txt");
+
+    let hands = parse_hands(input)?;
+
+    Ok(Solution {
+        day: 7,
+        part_1: total_winnings(&hands, Rules::Jacks),
+        part_2: total_winnings(&hands, Rules::Jokers),
+    })
+}
+
+/// What a `J` card stands for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Rules {
+    /// `J` is a jack, ranked between ten and queen.
+    Jacks,
+    /// `J` is a joker: it acts as whichever card makes the strongest hand type,
+    /// but is the weakest card when breaking ties.
+    Jokers,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum HandType {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    FullHouse,
+    FourOfAKind,
+    FiveOfAKind,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Hand {
+    cards: [char; 5],
+    bid: u64,
+}
+
+impl Hand {
+    pub fn hand_type(&self, rules: Rules) -> HandType {
+        let mut counts = [0_u8; 15];
+        let mut jokers = 0;
+        for card in self.cards {
+            match (card, rules) {
+                ('J', Rules::Jokers) => jokers += 1,
+                _ => counts[card_value(card, rules) as usize] += 1,
+            }
+        }
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        // Jokers always do best by joining the largest group.
+        match (counts[0] + jokers, counts[1]) {
+            (5, _) => HandType::FiveOfAKind,
+            (4, _) => HandType::FourOfAKind,
+            (3, 2) => HandType::FullHouse,
+            (3, _) => HandType::ThreeOfAKind,
+            (2, 2) => HandType::TwoPair,
+            (2, _) => HandType::OnePair,
+            _ => HandType::HighCard,
+        }
+    }
+
+    /// Hands are ordered by type, then card by card from the first.
+    fn strength(&self, rules: Rules) -> (HandType, [u8; 5]) {
+        (
+            self.hand_type(rules),
+            self.cards.map(|card| card_value(card, rules)),
+        )
+    }
+}
+
+fn card_value(card: char, rules: Rules) -> u8 {
+    match card {
+        'A' => 14,
+        'K' => 13,
+        'Q' => 12,
+        'J' => match rules {
+            Rules::Jacks => 11,
+            Rules::Jokers => 1,
+        },
+        'T' => 10,
+        _ => card.to_digit(10).unwrap() as u8,
+    }
+}
+
+/// Each hand's bid times its rank, where the weakest hand has rank 1.
+pub fn total_winnings(hands: &[Hand], rules: Rules) -> u64 {
+    let mut hands = hands.iter().collect::<Vec<_>>();
+    hands.sort_by_cached_key(|hand| hand.strength(rules));
+    hands
+        .iter()
+        .zip(1..)
+        .map(|(hand, rank)| hand.bid * rank)
+        .sum()
+}
+
+pub fn parse_hands(input: &str) -> Result<Vec<Hand>> {
+    input
+        .lines()
+        .map(|line| {
+            let (_, hand) = all_consuming(hand)(line)
+                .finish()
+                .map_err(|err| format!("invalid hand {line:?}: {err}"))?;
+            Ok(hand)
+        })
+        .collect()
+}
+
+fn hand(input: &str) -> IResult<&str, Hand> {
+    map_opt(
+        separated_pair(count(one_of("23456789TJQKA"), 5), space1, u64),
+        |(cards, bid)| {
+            Some(Hand {
+                cards: cards.try_into().ok()?,
+                bid,
+            })
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483";
+
+    fn parse(line: &str) -> Hand {
+        hand(line).unwrap().1
+    }
+
+    #[test]
+    fn test_total_winnings() -> Result<()> {
+        let hands = parse_hands(EXAMPLE)?;
+        assert_eq!(total_winnings(&hands, Rules::Jacks), 6440);
+        assert_eq!(total_winnings(&hands, Rules::Jokers), 5905);
+        Ok(())
+    }
+
+    #[test]
+    fn test_hand_type() {
+        let cases = [
+            ("AAAAA 1", HandType::FiveOfAKind, HandType::FiveOfAKind),
+            ("AA8AA 1", HandType::FourOfAKind, HandType::FourOfAKind),
+            ("23332 1", HandType::FullHouse, HandType::FullHouse),
+            ("TTT98 1", HandType::ThreeOfAKind, HandType::ThreeOfAKind),
+            ("23432 1", HandType::TwoPair, HandType::TwoPair),
+            ("A23A4 1", HandType::OnePair, HandType::OnePair),
+            ("23456 1", HandType::HighCard, HandType::HighCard),
+            ("T55J5 1", HandType::ThreeOfAKind, HandType::FourOfAKind),
+            ("KTJJT 1", HandType::TwoPair, HandType::FourOfAKind),
+            ("2345J 1", HandType::HighCard, HandType::OnePair),
+            ("22J33 1", HandType::TwoPair, HandType::FullHouse),
+            ("JJJJJ 1", HandType::FiveOfAKind, HandType::FiveOfAKind),
+        ];
+        for (line, jacks, jokers) in cases {
+            let hand = parse(line);
+            assert_eq!(hand.hand_type(Rules::Jacks), jacks, "{line}");
+            assert_eq!(hand.hand_type(Rules::Jokers), jokers, "{line}");
+        }
+    }
+
+    #[test]
+    fn test_strength() {
+        // Same type, so the first differing card decides.
+        assert!(parse("33332 1").strength(Rules::Jacks) > parse("2AAAA 1").strength(Rules::Jacks));
+        assert!(parse("77888 1").strength(Rules::Jacks) > parse("77788 1").strength(Rules::Jacks));
+        // A joker is the weakest card in a tie.
+        assert!(
+            parse("JKKK2 1").strength(Rules::Jokers) < parse("QQQQ2 1").strength(Rules::Jokers)
+        );
+        assert!(parse("JKKK2 1").strength(Rules::Jacks) < parse("QQQQ2 1").strength(Rules::Jacks));
+    }
+
+    #[test]
+    fn test_hand() {
+        assert_eq!(
+            hand("32T3K 765"),
+            Ok((
+                "",
+                Hand {
+                    cards: ['3', '2', 'T', '3', 'K'],
+                    bid: 765
+                }
+            ))
+        );
+        assert!(hand("32T3 765").is_err());
+        assert!(hand("32T3X 765").is_err());
+        assert!(parse_hands("32T3K 765\nnot a hand").is_err());
+        assert!(parse_hands("32T3K 765junk").is_err());
+    }
+}
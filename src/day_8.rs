@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+
+use nom::{
+    bytes::complete::tag,
+    character::complete::{alphanumeric1, newline, one_of},
+    combinator::map,
+    multi::{many1, separated_list1},
+    sequence::{delimited, separated_pair, terminated, tuple},
+    Finish, IResult,
+};
+
+use crate::{Result, Solution};
+
+pub fn solution() -> Result<Solution<u64>> {
+    let input = include_str!("../input/8.txt");
+
+    let network = Network::try_from(input)?;
+
+    let start = network.node("AAA").ok_or("no node AAA")?;
+    let part_1 = network
+        .trace(start, |name| name == "ZZZ")
+        .hits
+        .first()
+        .copied()
+        .ok_or("ZZZ is never reached from AAA")?;
+
+    let ghosts = network
+        .nodes()
+        .filter(|(_, name)| name.ends_with('A'))
+        .map(|(node, _)| network.trace(node, |name| name.ends_with('Z')))
+        .collect::<Vec<_>>();
+    let part_2 = earliest_common_arrival(&ghosts).ok_or("the ghosts never arrive together")?;
+
+    Ok(Solution {
+        day: 8,
+        part_1,
+        part_2,
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Direction {
+    Left,
+    Right,
+}
+
+/// The instructions and the node table, with every node name interned as
+/// its index in `names`.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Network {
+    instructions: Vec<Direction>,
+    names: Vec<String>,
+    /// Each name's index in `names`.
+    index: HashMap<String, usize>,
+    /// The left and right neighbours of each node.
+    edges: Vec<(usize, usize)>,
+}
+
+impl Network {
+    fn node(&self, name: &str) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    fn nodes(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.names.iter().map(String::as_str).enumerate()
+    }
+
+    fn next(&self, node: usize, step: usize) -> usize {
+        let (left, right) = self.edges[node];
+        match self.instructions[step % self.instructions.len()] {
+            Direction::Left => left,
+            Direction::Right => right,
+        }
+    }
+
+    /// Follows the instructions from `start` until a node is reached at the
+    /// same point in the instructions as before, from which the walk repeats.
+    fn trace(&self, start: usize, is_end: impl Fn(&str) -> bool) -> Ghost {
+        let mut seen = HashMap::new();
+        let mut hits = vec![];
+        let mut node = start;
+        let mut step = 0;
+        loop {
+            let state = (node, step % self.instructions.len());
+            if let Some(&cycle_start) = seen.get(&state) {
+                return Ghost {
+                    hits,
+                    cycle_start,
+                    period: step as u64 - cycle_start,
+                };
+            }
+            seen.insert(state, step as u64);
+            if is_end(&self.names[node]) {
+                hits.push(step as u64);
+            }
+            node = self.next(node, step);
+            step += 1;
+        }
+    }
+}
+
+impl TryFrom<&str> for Network {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(input: &str) -> Result<Self> {
+        let (_, (instructions, table)) = network(input)
+            .finish()
+            .map_err(|err| format!("invalid network: {err}"))?;
+        let index = table
+            .iter()
+            .enumerate()
+            .map(|(node, (name, _))| (name.to_string(), node))
+            .collect::<HashMap<_, _>>();
+        let lookup = |name: &str| {
+            index
+                .get(name)
+                .copied()
+                .ok_or_else(|| format!("node {name} is not defined"))
+        };
+        let edges = table
+            .iter()
+            .map(|(_, (left, right))| Ok((lookup(left)?, lookup(right)?)))
+            .collect::<std::result::Result<_, String>>()?;
+        Ok(Self {
+            instructions,
+            names: table.iter().map(|(name, _)| name.to_string()).collect(),
+            index,
+            edges,
+        })
+    }
+}
+
+/// The steps at which a walk is on an end node. Steps from `cycle_start` on
+/// repeat every `period` steps, so the walk is also on an end node at
+/// `hit + k * period` for every `hit` at or after `cycle_start`.
+#[derive(PartialEq, Eq, Debug)]
+struct Ghost {
+    hits: Vec<u64>,
+    cycle_start: u64,
+    period: u64,
+}
+
+impl Ghost {
+    fn arrives_at(&self, step: u64) -> bool {
+        let step = if step < self.cycle_start {
+            step
+        } else {
+            self.cycle_start + (step - self.cycle_start) % self.period
+        };
+        self.hits.contains(&step)
+    }
+
+    fn cyclic_hits(&self) -> impl Iterator<Item = u64> + '_ {
+        self.hits
+            .iter()
+            .copied()
+            .filter(|hit| *hit >= self.cycle_start)
+    }
+}
+
+/// The first step at which every ghost is on an end node at once.
+///
+/// When each ghost only ever reaches an end node at the end of each period,
+/// which is how puzzle inputs are built, this is the LCM of the periods. That
+/// needs the hit to repeat, so it must not come before the cycle starts.
+/// Otherwise the ghosts are checked together before they are all cycling,
+/// and after that every choice of one cyclic hit per ghost is combined with
+/// the Chinese remainder theorem.
+fn earliest_common_arrival(ghosts: &[Ghost]) -> Option<u64> {
+    if ghosts.is_empty() {
+        return None;
+    }
+    if ghosts
+        .iter()
+        .all(|ghost| ghost.hits == [ghost.period] && ghost.cycle_start <= ghost.period)
+    {
+        return ghosts
+            .iter()
+            .try_fold(1, |multiple, ghost| lcm(multiple, ghost.period));
+    }
+
+    let settled = ghosts.iter().map(|ghost| ghost.cycle_start).max()?;
+    let early = ghosts
+        .iter()
+        .flat_map(|ghost| ghost.hits.iter().copied())
+        .filter(|step| *step < settled)
+        .filter(|step| ghosts.iter().all(|ghost| ghost.arrives_at(*step)))
+        .min();
+    if early.is_some() {
+        return early;
+    }
+
+    ghosts
+        .iter()
+        .map(|ghost| {
+            ghost
+                .cyclic_hits()
+                .map(|hit| (hit % ghost.period, ghost.period))
+                .collect::<Vec<_>>()
+        })
+        .fold(vec![(0, 1)], |combined, congruences| {
+            combined
+                .iter()
+                .flat_map(|first| {
+                    congruences
+                        .iter()
+                        .filter_map(move |second| crt(*first, *second))
+                })
+                .collect()
+        })
+        .into_iter()
+        .filter_map(|(residue, modulus)| {
+            // The smallest step at or after `settled` with this residue.
+            let offset = settled % modulus;
+            let behind = if residue >= offset {
+                residue - offset
+            } else {
+                modulus - offset + residue
+            };
+            settled.checked_add(behind)
+        })
+        .min()
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn lcm(a: u64, b: u64) -> Option<u64> {
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+/// Combines `x ≡ a (mod m)` and `x ≡ b (mod n)` into a single congruence,
+/// or `None` if they have no common solution or its modulus overflows `u64`.
+/// The moduli need not be coprime.
+fn crt((a, m): (u64, u64), (b, n): (u64, u64)) -> Option<(u64, u64)> {
+    let g = gcd(m, n);
+    let difference = b as i128 - a as i128;
+    if difference % g as i128 != 0 {
+        return None;
+    }
+    let modulus = lcm(m, n)?;
+    let (m, n) = ((m / g) as i128, (n / g) as i128);
+    // a + m * g * k ≡ b (mod n * g) reduces to m * k ≡ difference / g (mod n).
+    let k = (difference / g as i128).rem_euclid(n) * inverse(m, n)? % n;
+    let residue = (a as i128 + m * g as i128 * k).rem_euclid(modulus as i128);
+    Some((residue as u64, modulus))
+}
+
+/// The inverse of `a` modulo `n`, by the extended Euclidean algorithm.
+fn inverse(a: i128, n: i128) -> Option<i128> {
+    let (mut old_r, mut r) = (a.rem_euclid(n), n);
+    let (mut old_s, mut s) = (1, 0);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+    }
+    (old_r == 1 || n == 1).then(|| old_s.rem_euclid(n))
+}
+
+type NodeTable<'a> = Vec<(&'a str, (&'a str, &'a str))>;
+
+fn network(input: &str) -> IResult<&str, (Vec<Direction>, NodeTable)> {
+    separated_pair(instructions, tag("\n\n"), separated_list1(newline, node))(input)
+}
+
+fn instructions(input: &str) -> IResult<&str, Vec<Direction>> {
+    many1(map(one_of("LR"), |direction| match direction {
+        'L' => Direction::Left,
+        _ => Direction::Right,
+    }))(input)
+}
+
+fn node(input: &str) -> IResult<&str, (&str, (&str, &str))> {
+    tuple((
+        terminated(alphanumeric1, tag(" = ")),
+        delimited(
+            tag("("),
+            separated_pair(alphanumeric1, tag(", "), alphanumeric1),
+            tag(")"),
+        ),
+    ))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)";
+
+    const GHOST_EXAMPLE: &str = "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)";
+
+    fn ghosts(network: &Network) -> Vec<Ghost> {
+        network
+            .nodes()
+            .filter(|(_, name)| name.ends_with('A'))
+            .map(|(node, _)| network.trace(node, |name| name.ends_with('Z')))
+            .collect()
+    }
+
+    #[test]
+    fn test_trace() -> Result<()> {
+        let network = Network::try_from(EXAMPLE)?;
+        let start = network.node("AAA").unwrap();
+        assert_eq!(
+            network.trace(start, |name| name == "ZZZ"),
+            Ghost {
+                hits: vec![6, 7, 8],
+                cycle_start: 6,
+                period: 3
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_earliest_common_arrival() -> Result<()> {
+        let network = Network::try_from(GHOST_EXAMPLE)?;
+        let ghosts = ghosts(&network);
+        assert_eq!(
+            ghosts,
+            vec![
+                Ghost {
+                    hits: vec![2],
+                    cycle_start: 1,
+                    period: 2
+                },
+                Ghost {
+                    hits: vec![3, 6],
+                    cycle_start: 1,
+                    period: 6
+                },
+            ]
+        );
+        assert_eq!(earliest_common_arrival(&ghosts), Some(6));
+        Ok(())
+    }
+
+    #[test]
+    fn test_earliest_common_arrival_lcm() {
+        let ghosts = [
+            Ghost {
+                hits: vec![4],
+                cycle_start: 1,
+                period: 4,
+            },
+            Ghost {
+                hits: vec![6],
+                cycle_start: 2,
+                period: 6,
+            },
+        ];
+        assert_eq!(earliest_common_arrival(&ghosts), Some(12));
+    }
+
+    #[test]
+    fn test_earliest_common_arrival_hit_before_cycle() -> Result<()> {
+        // The first ghost only passes 11Z on its way into the 11E-11F-11G
+        // loop, so its one hit never comes round again.
+        let network = Network::try_from(
+            "L
+
+11A = (11B, XXX)
+11B = (11C, XXX)
+11C = (11Z, XXX)
+11Z = (11D, XXX)
+11D = (11E, XXX)
+11E = (11F, XXX)
+11F = (11G, XXX)
+11G = (11E, XXX)
+22A = (22B, XXX)
+22B = (22Z, XXX)
+22Z = (22B, XXX)
+XXX = (XXX, XXX)",
+        )?;
+        let ghosts = ghosts(&network);
+        assert_eq!(
+            ghosts,
+            vec![
+                Ghost {
+                    hits: vec![3],
+                    cycle_start: 5,
+                    period: 3
+                },
+                Ghost {
+                    hits: vec![2],
+                    cycle_start: 1,
+                    period: 2
+                },
+            ]
+        );
+        assert_eq!(earliest_common_arrival(&ghosts), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_earliest_common_arrival_crt() {
+        // Arrives at 3, 8, 13, ... and at 4, 11, 18, ...
+        let ghosts = [
+            Ghost {
+                hits: vec![3],
+                cycle_start: 2,
+                period: 5,
+            },
+            Ghost {
+                hits: vec![1, 4],
+                cycle_start: 3,
+                period: 7,
+            },
+        ];
+        assert_eq!(earliest_common_arrival(&ghosts), Some(18));
+
+        // Both are on an end node at step 1, before either is cycling.
+        let ghosts = [
+            Ghost {
+                hits: vec![1, 3],
+                cycle_start: 2,
+                period: 5,
+            },
+            Ghost {
+                hits: vec![1, 4],
+                cycle_start: 3,
+                period: 7,
+            },
+        ];
+        assert_eq!(earliest_common_arrival(&ghosts), Some(1));
+
+        // Always an odd step apart.
+        let ghosts = [
+            Ghost {
+                hits: vec![2],
+                cycle_start: 0,
+                period: 4,
+            },
+            Ghost {
+                hits: vec![1],
+                cycle_start: 0,
+                period: 2,
+            },
+        ];
+        assert_eq!(earliest_common_arrival(&ghosts), None);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt((8, 15), (2, 7)), Some((23, 105)));
+        assert_eq!(crt((2, 4), (0, 6)), Some((6, 12)));
+        assert_eq!(crt((1, 4), (0, 6)), None);
+        assert_eq!(crt((0, 1), (5, 9)), Some((5, 9)));
+    }
+
+    #[test]
+    fn test_network() -> Result<()> {
+        let network = Network::try_from(EXAMPLE)?;
+        assert_eq!(
+            network,
+            Network {
+                instructions: vec![Direction::Left, Direction::Left, Direction::Right],
+                names: vec!["AAA".to_string(), "BBB".to_string(), "ZZZ".to_string()],
+                index: HashMap::from([
+                    ("AAA".to_string(), 0),
+                    ("BBB".to_string(), 1),
+                    ("ZZZ".to_string(), 2)
+                ]),
+                edges: vec![(1, 1), (0, 2), (2, 2)],
+            }
+        );
+
+        assert_eq!(network.node("BBB"), Some(1));
+        assert_eq!(network.node("CCC"), None);
+
+        let err = Network::try_from("L\n\nAAA = (BBB, AAA)").err().unwrap();
+        assert_eq!(err.to_string(), "node BBB is not defined");
+        Ok(())
+    }
+}
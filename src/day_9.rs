@@ -0,0 +1,205 @@
+use std::str::FromStr;
+
+use nom::{
+    character::complete::{i64, space1},
+    combinator::all_consuming,
+    multi::separated_list1,
+    Finish, IResult,
+};
+
+use crate::{Result, Solution};
+
+/// How the value after (or before) a history is found.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Extrapolator {
+    /// Take differences until they are all zero, then add back up.
+    Differences,
+    /// Evaluate the Lagrange polynomial through the history directly. With
+    /// the samples at `0..n`, its weights at `n` are signed binomial
+    /// coefficients, so no fractions are needed.
+    Binomial,
+}
+
+impl FromStr for Extrapolator {
+    type Err = String;
+
+    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
+        match input {
+            "differences" => Ok(Extrapolator::Differences),
+            "binomial" => Ok(Extrapolator::Binomial),
+            _ => Err(format!(
+                "unknown extrapolator {input:?}, expected differences or binomial"
+            )),
+        }
+    }
+}
+
+impl Extrapolator {
+    /// The value after `history`, or `None` on overflow.
+    pub fn next(&self, history: &[i64]) -> Option<i64> {
+        match self {
+            Extrapolator::Differences => next_by_differences(history),
+            Extrapolator::Binomial => next_by_binomial(history),
+        }
+    }
+
+    /// The value before `history`, or `None` on overflow. Reading the
+    /// history backwards gives the same polynomial mirrored, so this is
+    /// `next` of the reversed history.
+    pub fn previous(&self, history: &[i64]) -> Option<i64> {
+        let reversed = history.iter().rev().copied().collect::<Vec<_>>();
+        self.next(&reversed)
+    }
+}
+
+pub fn solution() -> Result<Solution<i64>> {
+    solution_with_extrapolator(Extrapolator::Differences)
+}
+
+pub fn solution_with_extrapolator(extrapolator: Extrapolator) -> Result<Solution<i64>> {
+    let input = include_str!("../input/9.txt");
+
+    let histories = parse_histories(input)?;
+
+    let part_1 = sum_extrapolated(&histories, |history| extrapolator.next(history))
+        .ok_or("extrapolating forwards overflowed")?;
+    let part_2 = sum_extrapolated(&histories, |history| extrapolator.previous(history))
+        .ok_or("extrapolating backwards overflowed")?;
+
+    Ok(Solution {
+        day: 9,
+        part_1,
+        part_2,
+    })
+}
+
+fn sum_extrapolated(
+    histories: &[Vec<i64>],
+    extrapolate: impl Fn(&[i64]) -> Option<i64>,
+) -> Option<i64> {
+    histories
+        .iter()
+        .try_fold(0_i64, |sum, history| sum.checked_add(extrapolate(history)?))
+}
+
+fn next_by_differences(history: &[i64]) -> Option<i64> {
+    if history.iter().all(|value| *value == 0) {
+        return Some(0);
+    }
+    let differences = history
+        .windows(2)
+        .map(|pair| pair[1].checked_sub(pair[0]))
+        .collect::<Option<Vec<_>>>()?;
+    history
+        .last()?
+        .checked_add(next_by_differences(&differences)?)
+}
+
+/// `sum((-1)^(n - 1 - i) * C(n, i) * history[i])` for a history of length
+/// `n`, worked in `i128` so only the result can overflow.
+fn next_by_binomial(history: &[i64]) -> Option<i64> {
+    let n = history.len() as i128;
+    let mut coefficient = 1_i128;
+    let mut next = 0_i128;
+    for (i, value) in history.iter().enumerate() {
+        let i = i as i128;
+        let sign = if (n - 1 - i) % 2 == 0 { 1 } else { -1 };
+        next = next.checked_add(sign * coefficient.checked_mul(*value as i128)?)?;
+        // C(n, i + 1) from C(n, i).
+        coefficient = coefficient.checked_mul(n - i)? / (i + 1);
+    }
+    next.try_into().ok()
+}
+
+fn parse_histories(input: &str) -> Result<Vec<Vec<i64>>> {
+    input
+        .lines()
+        .map(|line| {
+            let (_, history) = all_consuming(history)(line)
+                .finish()
+                .map_err(|err| format!("invalid history {line:?}: {err}"))?;
+            Ok(history)
+        })
+        .collect()
+}
+
+fn history(input: &str) -> IResult<&str, Vec<i64>> {
+    separated_list1(space1, i64)(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "0 3 6 9 12 15
+1 3 6 10 15 21
+10 13 16 21 30 45";
+
+    const EXTRAPOLATORS: [Extrapolator; 2] = [Extrapolator::Differences, Extrapolator::Binomial];
+
+    #[test]
+    fn test_sum_extrapolated() -> Result<()> {
+        let histories = parse_histories(EXAMPLE)?;
+        for extrapolator in EXTRAPOLATORS {
+            assert_eq!(
+                sum_extrapolated(&histories, |history| extrapolator.next(history)),
+                Some(114)
+            );
+            assert_eq!(
+                sum_extrapolated(&histories, |history| extrapolator.previous(history)),
+                Some(2)
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_extrapolate() {
+        for extrapolator in EXTRAPOLATORS {
+            assert_eq!(extrapolator.next(&[0, 3, 6, 9, 12, 15]), Some(18));
+            assert_eq!(extrapolator.next(&[1, 3, 6, 10, 15, 21]), Some(28));
+            assert_eq!(extrapolator.next(&[10, 13, 16, 21, 30, 45]), Some(68));
+            assert_eq!(extrapolator.previous(&[10, 13, 16, 21, 30, 45]), Some(5));
+            assert_eq!(extrapolator.next(&[-4, -4]), Some(-4));
+            assert_eq!(extrapolator.next(&[7]), Some(7));
+            assert_eq!(extrapolator.next(&[]), Some(0));
+        }
+    }
+
+    #[test]
+    fn test_extrapolate_overflow() {
+        for extrapolator in EXTRAPOLATORS {
+            assert_eq!(extrapolator.next(&[0, i64::MAX]), None);
+            assert_eq!(extrapolator.previous(&[i64::MIN, 0]), None);
+            assert_eq!(
+                extrapolator.next(&[i64::MAX - 2, i64::MAX - 1]),
+                Some(i64::MAX)
+            );
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        assert_eq!(history("0 -3 6"), Ok(("", vec![0, -3, 6])));
+        assert!(parse_histories("1 2\n1 x").is_err());
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn extrapolators_agree(history in prop::collection::vec(-1000_i64..1000, 0..16)) {
+            for extrapolate in [Extrapolator::next, Extrapolator::previous] {
+                prop_assert_eq!(
+                    extrapolate(&Extrapolator::Differences, &history),
+                    extrapolate(&Extrapolator::Binomial, &history)
+                );
+            }
+        }
+    }
+}
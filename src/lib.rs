@@ -1,9 +1,17 @@
+#![feature(test)]
+
 pub mod day_1;
+pub mod day_10;
+pub mod day_11;
+pub mod day_12;
 pub mod day_2;
 pub mod day_3;
 pub mod day_4;
 pub mod day_5;
 pub mod day_6;
+pub mod day_7;
+pub mod day_8;
+pub mod day_9;
 
 use std::{
     fmt::Display,
@@ -20,7 +28,7 @@ pub struct Solution<T: Display> {
     part_2: T,
 }
 
-impl <T: Display> Display for Solution<T> {
+impl<T: Display> Display for Solution<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(
             f,
@@ -33,6 +41,34 @@ impl <T: Display> Display for Solution<T> {
     }
 }
 
+/// Whether problems found while validating an input are reported or fatal.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strictness {
+    Warn,
+    Deny,
+}
+
+impl Strictness {
+    /// Prints each issue as a warning, or fails with all of them.
+    pub fn check<T: Display>(&self, issues: &[T]) -> Result<()> {
+        match self {
+            Strictness::Warn => {
+                for issue in issues {
+                    eprintln!("warning: {issue}");
+                }
+                Ok(())
+            }
+            Strictness::Deny if !issues.is_empty() => Err(issues
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .into()),
+            Strictness::Deny => Ok(()),
+        }
+    }
+}
+
 // Load lines from a file
 pub fn input_lines(day: u8) -> Result<Lines<BufReader<File>>> {
     let file = File::open(format!("./input/{day}.txt"))?;
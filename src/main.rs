@@ -1,22 +1,149 @@
-use aoc_2023::Result;
-use clap::Parser;
+use std::io::IsTerminal;
+
+use aoc_2023::{
+    day_2::CubeCount,
+    day_3::{RenderStyle, SchematicConfig},
+    day_5::Part2Strategy,
+    day_9::Extrapolator,
+    Result, Strictness,
+};
+use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(short)]
-    day: u8,
+    #[arg(short, required = true)]
+    day: Option<u8>,
+
+    /// Cubes in the bag for day 2, e.g. "12 red, 13 green, 14 blue"
+    #[arg(long)]
+    bag: Option<CubeCount>,
+
+    /// Print an annotated view of how the answer was reached
+    #[arg(long)]
+    explain: bool,
+
+    /// Fail on suspicious input instead of printing warnings
+    #[arg(long)]
+    strict: bool,
+
+    /// Day 5 part 2 search: "forward" from seeds, "inverse" from locations or
+    /// "composed" through a single collapsed map
+    #[arg(long, default_value = "composed")]
+    strategy: Part2Strategy,
+
+    /// Day 6 boat speed gained per ms the button is held, in mm/ms
+    #[arg(long, default_value_t = 1)]
+    acceleration: u64,
+
+    /// Day 9 extrapolation: "differences" or "binomial"
+    #[arg(long, default_value = "differences")]
+    extrapolator: Extrapolator,
+
+    /// Day 11 part 2 width of each empty row and column
+    #[arg(long, default_value_t = 1_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+    expansion: u64,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Queries over the day 2 games
+    #[command(subcommand)]
+    Day2(Day2Command),
+}
+
+#[derive(Subcommand)]
+enum Day2Command {
+    /// Print bag limits, per-game max draws and round sizes
+    Stats {
+        /// Number of games the reported bag limits should admit
+        #[arg(short, default_value_t = 0)]
+        k: usize,
+    },
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    match args.day {
+    if let Some(command) = args.command {
+        match command {
+            Command::Day2(Day2Command::Stats { k }) => {
+                print!("{}", aoc_2023::day_2::Games::load()?.stats(k))
+            }
+        }
+        return Ok(());
+    }
+    let day = args.day.unwrap();
+    let strictness = if args.strict {
+        Strictness::Deny
+    } else {
+        Strictness::Warn
+    };
+    match day {
         1 => println!("{}", aoc_2023::day_1::solution()?),
-        2 => println!("{}", aoc_2023::day_2::solution()?),
-        3 => println!("{}", aoc_2023::day_3::solution()?),
-        4 => println!("{}", aoc_2023::day_4::solution()?),
-        5 => println!("{}", aoc_2023::day_5::solution()?),
-        6 => println!("{}", aoc_2023::day_6::solution()?),
-        _ => println!("Day {} not implemented", args.day),
+        2 => match &args.bag {
+            Some(bag) => println!("{}", aoc_2023::day_2::solution_with_bag(bag)?),
+            None => println!("{}", aoc_2023::day_2::solution()?),
+        },
+        3 => {
+            println!("{}", aoc_2023::day_3::solution()?);
+            if args.explain {
+                let style = if std::io::stdout().is_terminal() {
+                    RenderStyle::Ansi
+                } else {
+                    RenderStyle::Plain
+                };
+                print!(
+                    "{}",
+                    aoc_2023::day_3::explain(&SchematicConfig::default(), style)?
+                );
+            }
+        }
+        4 => {
+            println!("{}", aoc_2023::day_4::solution_with_strictness(strictness)?);
+            if args.explain {
+                print!("{}", aoc_2023::day_4::explain()?);
+            }
+        }
+        5 => {
+            println!(
+                "{}",
+                aoc_2023::day_5::solution_with_options(args.strategy, strictness)?
+            );
+            if args.explain {
+                print!("{}", aoc_2023::day_5::analysis()?);
+                print!("{}", aoc_2023::day_5::composed_table()?);
+            }
+        }
+        6 => {
+            println!(
+                "{}",
+                aoc_2023::day_6::solution_with_acceleration(args.acceleration)?
+            );
+            if args.explain {
+                print!("{}", aoc_2023::day_6::explain(args.acceleration)?);
+            }
+        }
+        7 => println!("{}", aoc_2023::day_7::solution()?),
+        8 => println!("{}", aoc_2023::day_8::solution()?),
+        9 => println!(
+            "{}",
+            aoc_2023::day_9::solution_with_extrapolator(args.extrapolator)?
+        ),
+        10 => {
+            println!("{}", aoc_2023::day_10::solution()?);
+            if args.explain {
+                print!("{}", aoc_2023::day_10::explain()?);
+            }
+        }
+        11 => println!(
+            "{}",
+            aoc_2023::day_11::solution_with_expansion(args.expansion)?
+        ),
+        12 => println!("{}", aoc_2023::day_12::solution()?),
+        _ => println!("Day {} not implemented", day),
     }
     Ok(())
 }